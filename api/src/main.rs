@@ -1,10 +1,10 @@
-use parsing::{parse_json, JsonType, FromJson};
+use parsing::{parse_json, JsonType};
 
 use std::{
     collections::HashMap, io::{prelude::*, BufReader}, net::{TcpListener, TcpStream}
 };
 
-use parsing::{HttpRequest, HttpMethod, HttpPath, HttpVersion, HttpResponse, KnownHeader, HttpContentType, HttpPlatform, HttpStatusCode, read_http_request, write_http_request, write_http_response};
+use parsing::{HttpRequest, HttpMethod, HttpPath, HttpVersion, HttpResponse, HttpBody, KnownHeader, HttpContentType, HttpStatusCode, Router, html_escape, read_http_request, write_http_request, write_http_response};
 
 fn main() {
 
@@ -21,6 +21,11 @@ fn main() {
         version: HttpVersion::HTTP11,
         headers: headers,
         body: None,
+        wildcard: None,
+        raw_request_line: String::new(),
+        request_id: None,
+        content_type_params: HashMap::new(),
+        raw_headers: Vec::new(),
     };
 
     match write_http_request(req) {
@@ -43,69 +48,99 @@ fn main() {
     }
 }
 
-fn run_custom_http_server() {
-let platform = HttpPlatform::new(|req| {
-        println!("Handling request for path: {}", req.path.full_path);
-        let mut headers = HashMap::<String, KnownHeader>::new();
-        
-        match req.path.path.as_str() {
-            "/" => {
-                headers.insert("Content-Type".to_string(), KnownHeader::ContentType(HttpContentType::TextHtml));
-                headers.insert("Content-Length".to_string(), KnownHeader::ContentLength("<h1>Welcome to the Rust HTTP Server!</h1>".bytes().len()));
-                return HttpResponse {
-                    version: req.version,
-                    status_code: HttpStatusCode::OK,
-                    headers: headers,
-                    body: Some("<h1>Welcome to the Rust HTTP Server!</h1>".to_string()),
-                }
-            },
-            "/json" => {
-                let json_str = r#"
-                {
-                    "name": "John Doe",
-                    "age": 30,
-                    "is_student": false,        
-                    "courses": ["Math", "Science", "History"],
-                    "address": {
-                        "street": "123 Main St",
-                        "city": "Anytown",
-                        "zip": "12345"
-                    }
-                }
-                "#;
-
-                headers.insert("Content-Type".to_string(), KnownHeader::ContentType(HttpContentType::ApplicationJson));
-                headers.insert("Content-Length".to_string(), KnownHeader::ContentLength(json_str.bytes().len()));
-                
-                return HttpResponse {
-                    version: req.version,
-                    status_code: HttpStatusCode::OK,
-                    headers: headers,
-                    body: Some(json_str.to_string()),
-                }
-            },
-            _ => {
-
-                headers.insert("Content-Type".to_string(), KnownHeader::ContentType(HttpContentType::TextHtml));
-                headers.insert("Content-Length".to_string(), KnownHeader::ContentLength("<h1>404 Not Found</h1>".bytes().len()));
-                return HttpResponse {
-                    version: req.version,
-                    status_code: HttpStatusCode::NotFound,
-                    headers: headers,
-                    body: Some("<h1>404 Not Found</h1>".to_string()),
-                }
-            }
+fn index(req: HttpRequest) -> HttpResponse {
+    let mut headers = HashMap::<String, KnownHeader>::new();
+    headers.insert("Content-Type".to_string(), KnownHeader::ContentType(HttpContentType::TextHtml));
+    headers.insert("Content-Length".to_string(), KnownHeader::ContentLength("<h1>Welcome to the Rust HTTP Server!</h1>".len()));
+
+    HttpResponse {
+        version: req.version,
+        status_code: HttpStatusCode::OK,
+        headers,
+        cookies: Vec::new(),
+        body: Some(HttpBody::Text("<h1>Welcome to the Rust HTTP Server!</h1>".to_string())),
+    }
+}
+
+// The name comes straight from the path, so it is escaped before going into the page
+fn hello(req: HttpRequest) -> HttpResponse {
+    let name = req.wildcard.unwrap_or_default();
+    let body = format!("<h1>Hello, {}!</h1>", html_escape(&name));
+
+    let mut headers = HashMap::<String, KnownHeader>::new();
+    headers.insert("Content-Type".to_string(), KnownHeader::ContentType(HttpContentType::TextHtml));
+    headers.insert("Content-Length".to_string(), KnownHeader::ContentLength(body.len()));
+
+    HttpResponse {
+        version: req.version,
+        status_code: HttpStatusCode::OK,
+        headers,
+        cookies: Vec::new(),
+        body: Some(HttpBody::Text(body)),
+    }
+}
+
+fn json_index(req: HttpRequest) -> HttpResponse {
+    let mut address = HashMap::new();
+    address.insert("street".to_string(), JsonType::String("123 Main St".to_string()));
+    address.insert("city".to_string(), JsonType::String("Anytown".to_string()));
+    address.insert("zip".to_string(), JsonType::String("12345".to_string()));
+
+    let mut person = HashMap::new();
+    person.insert("name".to_string(), JsonType::String("John Doe".to_string()));
+    person.insert("age".to_string(), JsonType::Number(30));
+    person.insert("is_student".to_string(), JsonType::Boolean(false));
+    person.insert("courses".to_string(), JsonType::Array(vec![
+        JsonType::String("Math".to_string()),
+        JsonType::String("Science".to_string()),
+        JsonType::String("History".to_string()),
+    ]));
+    person.insert("address".to_string(), JsonType::Object(address));
+
+    let mut response = HttpResponse::json(HttpStatusCode::OK, &JsonType::Object(person));
+    response.version = req.version;
+    response
+}
+
+// Echoes a JSON body back wrapped together with the method that delivered it
+fn update_data(req: HttpRequest) -> HttpResponse {
+    let method = match req.method {
+        HttpMethod::PUT => "PUT",
+        _ => "PATCH",
+    };
+
+    let data = match parse_json(req.body.as_deref().unwrap_or_default()) {
+        Ok(data) => data,
+        Err(e) => {
+            let mut response = HttpResponse::text(HttpStatusCode::BadRequest, &format!("Invalid JSON: {:?}", e));
+            response.version = req.version;
+            return response;
         }
-    });
+    };
 
-    let listener = TcpListener::bind("127.0.0.1:7878").unwrap();
-    for stream in listener.incoming() {
-        let stream = stream.unwrap();
+    let mut document = HashMap::new();
+    document.insert("method".to_string(), JsonType::String(method.to_string()));
+    document.insert("data".to_string(), data);
 
-        let cln = platform.clone();
-        std::thread::spawn(move || {
-            cln.handle_request(stream);
-        });
+    let mut response = HttpResponse::json(HttpStatusCode::OK, &JsonType::Object(document));
+    response.version = req.version;
+    response
+}
+
+fn router() -> Router {
+    Router::new()
+        .route(HttpMethod::GET, "/", index)
+        .route(HttpMethod::GET, "/json", json_index)
+        .route(HttpMethod::GET, "/hello/*", hello)
+        .route(HttpMethod::PUT, "/data", update_data)
+        .route(HttpMethod::PATCH, "/data", update_data)
+}
+
+fn run_custom_http_server() {
+    let platform = router().into_platform();
+
+    if let Err(e) = platform.serve("127.0.0.1:7878") {
+        eprintln!("Server stopped: {}", e);
     }
 }
 
@@ -161,137 +196,52 @@ fn http_server() {
     }
 }
 
-fn json_testing() {
-        let json_str = r#"
-    {
-        "name": "John Doe",
-        "age": 30,
-        "is_student": false,
-        "courses": ["Math", "Science", "History"],
-        "address": {
-            "street": "123 Main St",
-            "city": "Anytown",
-            "zip": "12345"
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parsing::{read_http_response, start_test_platform};
+
+    fn send(request: &str) -> HttpResponse {
+        let server = start_test_platform(router().into_platform());
+
+        let mut stream = TcpStream::connect(server.addr()).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        read_http_response(response.as_str()).unwrap()
     }
-    "#;
-
-    let parse_result = parse_json(json_str);
-    match parse_result {
-        Ok(json) => {
-            let person = Person::from_json(&json);
-            println!("Name: {}", person.name);
-            println!("Age: {}", person.age);
-            println!("Is Student: {}", person.is_student);
-            println!("Courses: {:?}", person.courses);
-            println!("Address: {}, {}, {}", person.address.street, person.address.city, person.address.zip);
-        },
-        Err(e) => {
-            println!("Failed to parse JSON: {:?}", e);
-        }
+
+    fn json_request(method: &str, body: &str) -> String {
+        format!(
+            "{} /data HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            body.len(),
+            body
+        )
     }
-}
 
-struct Person {
-    name: String,
-    age: i32,
-    is_student: bool,
-    courses: Vec<String>,
-    address: Address,
-}
+    #[test]
+    fn put_and_patch_echo_json_body() {
+        let response = send(&json_request("PUT", r#"{"name": "John Doe", "age": 30}"#));
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+        assert_eq!(
+            response.body.and_then(|body| body.as_text().map(str::to_string)),
+            Some(r#"{"data":{"age":30,"name":"John Doe"},"method":"PUT"}"#.to_string())
+        );
 
-impl FromJson for Person {
-    fn from_json(json: &JsonType) -> Self {
-        match json {
-            JsonType::Object(obj) => {
-                let name = if let Some(JsonType::String(s)) = obj.get("name") {
-                    s.clone()
-                } else {
-                    "".to_string()
-                };
-
-                let age = if let Some(JsonType::Number(n)) = obj.get("age") {
-                    *n as i32
-                } else {
-                    0
-                };
-
-                let is_student = if let Some(JsonType::Boolean(b)) = obj.get("is_student") {
-                    *b
-                } else {
-                    false
-                };
-
-                let courses = if let Some(JsonType::Array(arr)) = obj.get("courses") {
-                    arr.iter().filter_map(|item| {
-                        if let JsonType::String(s) = item {
-                            Some(s.clone())
-                        } else {
-                            None
-                        }
-                    }).collect()
-                } else {
-                    vec![]
-                };
-
-                let address = if let Some(addr_json) = obj.get("address") {
-                    Address::from_json(addr_json.clone())
-                } else {
-                    Address {
-                        street: "".to_string(),
-                        city: "".to_string(),
-                        zip: "".to_string(),
-                    }
-                };
-
-                Person {
-                    name,
-                    age,
-                    is_student,
-                    courses,
-                    address,
-                }
-            },
-            _ => panic!("Expected a JSON object"),
-        }
+        let response = send(&json_request("PATCH", r#"{"age": 31}"#));
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+        assert_eq!(
+            response.body.and_then(|body| body.as_text().map(str::to_string)),
+            Some(r#"{"data":{"age":31},"method":"PATCH"}"#.to_string())
+        );
     }
-}
-
-struct Address {
-    street: String,
-    city: String,
-    zip: String,
-}
 
-impl FromJson for Address {
-    fn from_json(json: &JsonType) -> Self {
-        match json {
-            JsonType::Object(obj) => {
-                let street = if let Some(JsonType::String(s)) = obj.get("street") {
-                    s.clone()
-                } else {
-                    "".to_string()
-                };
-
-                let city = if let Some(JsonType::String(s)) = obj.get("city") {
-                    s.clone()
-                } else {
-                    "".to_string()
-                };
-
-                let zip = if let Some(JsonType::String(s)) = obj.get("zip") {
-                    s.clone()
-                } else {
-                    "".to_string()
-                };
-
-                Address {
-                    street,
-                    city,
-                    zip,
-                }
-            },
-            _ => panic!("Expected a JSON object"),
-        }
+    #[test]
+    fn put_invalid_json_is_rejected() {
+        let response = send(&json_request("PUT", r#"{"name": }"#));
+        assert_eq!(response.status_code, HttpStatusCode::BadRequest);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,113 @@
+// Parser benchmarks, run with `cargo bench`. Plain std timing so no extra dependencies
+// are needed: every case is warmed up, then timed over enough iterations to fill about
+// half a second, and the mean time per parse is reported.
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use parsing::parse_json;
+
+const REAL_WORLD_OBJECT: &str = r#"
+        {
+    "_id": "68d7cd3e0c429cb0c5dde37b",
+    "index": 2,
+    "guid": "36f8338a-f478-409e-9c62-dd4a996bd708",
+    "isActive": true,
+    "balance": "$3,324.92",
+    "picture": "http://placehold.it/32x32",
+    "age": 22,
+    "eyeColor": "brown",
+    "name": "Myrtle Terrell",
+    "gender": "female",
+    "company": "ZENSUS",
+    "email": "myrtleterrell@zensus.com",
+    "phone": "+1 (923) 486-3743",
+    "address": "511 Nova Court, Tetherow, Indiana, 5807",
+    "about": "Non cillum adipisicing consequat sunt tempor pariatur occaecat sint laborum sit. Exercitation dolore duis occaecat proident elit enim. Nostrud aliquip incididunt reprehenderit ipsum et excepteur exercitation.\r\n",
+    "registered": "2020-05-02T02:37:23 -02:00",
+    "latitude": 56.2324,
+    "longitude": -140.453,
+    "tags": [
+      "reprehenderit",
+      "laboris",
+      "duis",
+      "aute",
+      "velit",
+      "dolore",
+      "in"
+    ],
+    "friends": [
+      {
+        "id": 0,
+        "name": "Isabella Lawrence"
+      },
+      {
+        "id": 1,
+        "name": "Eve Ayala"
+      },
+      {
+        "id": 2,
+        "name": "Marion Lucas"
+      }
+    ],
+    "greeting": "Hello, Myrtle Terrell! You have 2 unread messages.",
+    "favoriteFruit": "apple"
+  }"#;
+
+const TARGET_DURATION: Duration = Duration::from_millis(500);
+
+fn bench(name: &str, input: &str) {
+    for _ in 0..10 {
+        parse_json(black_box(input)).unwrap();
+    }
+
+    // grow the batch until one run is long enough to time reliably
+    let mut iterations: u32 = 1;
+    let elapsed = loop {
+        let start = Instant::now();
+        for _ in 0..iterations {
+            black_box(parse_json(black_box(input)).unwrap());
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= TARGET_DURATION || iterations >= 1 << 24 {
+            break elapsed;
+        }
+        iterations *= 2;
+    };
+
+    let per_iteration = elapsed / iterations;
+    let throughput = input.len() as f64 / per_iteration.as_secs_f64() / (1024.0 * 1024.0);
+    println!(
+        "{:<24} {:>12?}/parse {:>10.1} MiB/s ({} bytes, {} iterations)",
+        name,
+        per_iteration,
+        throughput,
+        input.len(),
+        iterations
+    );
+}
+
+// An array of 10,000 small objects, roughly 850 KiB
+fn large_array() -> String {
+    let items: Vec<String> = (0..10_000)
+        .map(|i| format!(r#"{{"id": {}, "name": "item {}", "price": {}.5, "tags": ["a", "b"], "active": {}}}"#, i, i, i, i % 2 == 0))
+        .collect();
+    format!("[{}]", items.join(", "))
+}
+
+// Objects nested 100 levels deep, below the default max_depth of 128
+fn deeply_nested_object() -> String {
+    let depth = 100;
+    let mut json = String::new();
+    for level in 0..depth {
+        json.push_str(&format!(r#"{{"level": {}, "child": "#, level));
+    }
+    json.push_str("null");
+    json.push_str(&"}".repeat(depth));
+    json
+}
+
+fn main() {
+    bench("small_object", REAL_WORLD_OBJECT);
+    bench("large_array", &large_array());
+    bench("deeply_nested_object", &deeply_nested_object());
+}
@@ -0,0 +1,107 @@
+use crate::http::HttpRequestError;
+
+// Standard alphabet with '=' padding (RFC 4648 section 4)
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn base64_encode(input: &[u8]) -> String {
+    let mut output = String::with_capacity(input.len().div_ceil(3) * 4);
+
+    for chunk in input.chunks(3) {
+        let b0 = chunk[0] as u32;
+        let b1 = chunk.get(1).copied().unwrap_or(0) as u32;
+        let b2 = chunk.get(2).copied().unwrap_or(0) as u32;
+        let triple = (b0 << 16) | (b1 << 8) | b2;
+
+        output.push(ALPHABET[(triple >> 18) as usize & 0x3f] as char);
+        output.push(ALPHABET[(triple >> 12) as usize & 0x3f] as char);
+
+        if chunk.len() > 1 {
+            output.push(ALPHABET[(triple >> 6) as usize & 0x3f] as char);
+        } else {
+            output.push('=');
+        }
+
+        if chunk.len() > 2 {
+            output.push(ALPHABET[triple as usize & 0x3f] as char);
+        } else {
+            output.push('=');
+        }
+    }
+
+    output
+}
+
+pub fn base64_decode(input: &str) -> Result<Vec<u8>, HttpRequestError> {
+    let bytes = input.as_bytes();
+    if !bytes.len().is_multiple_of(4) {
+        return Err(HttpRequestError::InvalidRequest(format!("Invalid base64 length: {}", bytes.len())));
+    }
+
+    let mut output = Vec::with_capacity(bytes.len() / 4 * 3);
+
+    for (index, chunk) in bytes.chunks(4).enumerate() {
+        let is_last = index == bytes.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+
+        // padding only closes the final group and never takes more than two characters
+        if padding > 2 || (padding > 0 && !is_last) {
+            return Err(HttpRequestError::InvalidRequest("Invalid base64 padding".to_string()));
+        }
+
+        let mut triple: u32 = 0;
+        for &c in &chunk[..4 - padding] {
+            let value = match decode_char(c) {
+                Some(value) => value,
+                None => return Err(HttpRequestError::InvalidRequest(format!("Invalid base64 character: {}", c as char))),
+            };
+            triple = (triple << 6) | value;
+        }
+        triple <<= 6 * padding as u32;
+
+        output.push((triple >> 16) as u8);
+        if padding < 2 {
+            output.push((triple >> 8) as u8);
+        }
+        if padding < 1 {
+            output.push(triple as u8);
+        }
+    }
+
+    Ok(output)
+}
+
+fn decode_char(c: u8) -> Option<u32> {
+    ALPHABET.iter().position(|&a| a == c).map(|position| position as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_known_vector() {
+        assert_eq!(base64_encode(b"Aladdin:open sesame"), "QWxhZGRpbjpvcGVuIHNlc2FtZQ==");
+        assert_eq!(base64_decode("QWxhZGRpbjpvcGVuIHNlc2FtZQ=="), Ok(b"Aladdin:open sesame".to_vec()));
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+    }
+
+    #[test]
+    fn base64_binary_round_trip() {
+        let data: Vec<u8> = (0..=255).collect();
+
+        for length in 0..data.len() {
+            let encoded = base64_encode(&data[..length]);
+            assert_eq!(base64_decode(&encoded), Ok(data[..length].to_vec()));
+        }
+    }
+
+    #[test]
+    fn base64_decode_invalid_input() {
+        assert!(base64_decode("abc").is_err());
+        assert!(base64_decode("ab!d").is_err());
+        assert!(base64_decode("a===").is_err());
+        assert!(base64_decode("ab==abcd").is_err());
+    }
+}
@@ -0,0 +1,394 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    net::TcpStream,
+};
+
+use crate::http::{
+    find_header, has_connection_token, is_chunked, read_http_response, read_request_body, read_request_head,
+    BodyReadError, HttpBody, HttpMethod, HttpPath, HttpRequest, HttpRequestError, HttpResponse, HttpStatusCode,
+    HttpVersion, KnownHeader, DEFAULT_MAX_BODY_SIZE, write_http_request,
+};
+
+const MAX_RESPONSE_HEAD: usize = 64 * 1024;
+
+// A minimal HTTP/1.1 client for plain http:// URLs. Connections are kept alive and
+// pooled per host:port, so repeated requests to the same server reuse one socket.
+#[derive(Default)]
+pub struct HttpClient {
+    // idle keep-alive connections by "host:port"
+    idle: HashMap<String, Vec<TcpStream>>,
+    // redirects followed per request, None returns redirect responses as they are
+    max_redirects: Option<usize>,
+}
+
+impl HttpClient {
+    pub fn new() -> HttpClient {
+        HttpClient::default()
+    }
+
+    // Follows 301, 302, 303, 307 and 308 responses to their Location, at most max_redirects
+    // times per request before giving up with an error
+    pub fn with_redirects(mut self, max_redirects: usize) -> HttpClient {
+        self.max_redirects = Some(max_redirects);
+        self
+    }
+
+    pub fn get(&mut self, url: &str) -> Result<HttpResponse, HttpRequestError> {
+        self.request(HttpMethod::GET, url, None)
+    }
+
+    pub fn request(&mut self, mut method: HttpMethod, url: &str, mut body: Option<String>) -> Result<HttpResponse, HttpRequestError> {
+        let (mut authority, mut target) = split_url(url)?;
+        let mut redirects = 0;
+
+        loop {
+            let response = self.send(&authority, build_request(method.clone(), &authority, &target, body.clone()))?;
+
+            let (max_redirects, location) = match (self.max_redirects, redirect_location(&response)) {
+                (Some(max_redirects), Some(location)) => (max_redirects, location),
+                _ => return Ok(response),
+            };
+            if redirects == max_redirects {
+                return Err(HttpRequestError::InvalidRequest(format!("Too many redirects (limit {})", max_redirects)));
+            }
+            redirects += 1;
+
+            // 303 asks for the result with a GET, the other codes repeat the request as it was
+            if response.status_code == HttpStatusCode::SeeOther && method != HttpMethod::HEAD {
+                method = HttpMethod::GET;
+                body = None;
+            }
+            (authority, target) = resolve_location(&location, authority, &target)?;
+        }
+    }
+
+    // Number of pooled connections to authority ("host:port") waiting for the next request
+    pub fn idle_connections(&self, authority: &str) -> usize {
+        self.idle.get(&pool_key(authority)).map_or(0, Vec::len)
+    }
+
+    fn send(&mut self, authority: &str, request: HttpRequest) -> Result<HttpResponse, HttpRequestError> {
+        let key = pool_key(authority);
+        let head_only = request.method == HttpMethod::HEAD;
+        let bytes = write_http_request(request)?;
+
+        // the server may close an idle connection at any time, a request that finds it gone
+        // before any response arrived is sent again on a fresh connection
+        while let Some(mut stream) = self.checkout(&key) {
+            if let Some((response, reusable)) = exchange(&mut stream, bytes.as_bytes(), head_only)? {
+                if reusable {
+                    self.idle.entry(key).or_default().push(stream);
+                }
+                return Ok(response);
+            }
+        }
+
+        let mut stream = TcpStream::connect(&key).map_err(|e| io_error("Failed to connect", e))?;
+        match exchange(&mut stream, bytes.as_bytes(), head_only)? {
+            Some((response, reusable)) => {
+                if reusable {
+                    self.idle.entry(key).or_default().push(stream);
+                }
+                Ok(response)
+            }
+            None => Err(HttpRequestError::InvalidRequest("Connection closed before a response was received".to_string())),
+        }
+    }
+
+    // Takes an idle connection out of the pool, dropping the ones the server has closed
+    fn checkout(&mut self, key: &str) -> Option<TcpStream> {
+        let idle = self.idle.get_mut(key)?;
+        while let Some(stream) = idle.pop() {
+            if is_open(&stream) {
+                return Some(stream);
+            }
+        }
+        None
+    }
+}
+
+// An idle connection has nothing to read: EOF means the server closed it and unexpected
+// data means it is out of step with us, either way it can't be reused
+fn is_open(stream: &TcpStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return false;
+    }
+    let open = matches!(stream.peek(&mut [0; 1]), Err(e) if e.kind() == io::ErrorKind::WouldBlock);
+    open && stream.set_nonblocking(false).is_ok()
+}
+
+// Writes the request and reads the response. None when the connection was closed before
+// any of the response arrived, otherwise the response and whether the connection can
+// carry another request.
+fn exchange(stream: &mut TcpStream, request: &[u8], head_only: bool) -> Result<Option<(HttpResponse, bool)>, HttpRequestError> {
+    if stream.write_all(request).and_then(|_| stream.flush()).is_err() {
+        return Ok(None);
+    }
+
+    let mut buffer = Vec::new();
+    let head_length = match read_request_head(stream, &mut buffer, MAX_RESPONSE_HEAD) {
+        Ok(length) => length,
+        Err(BodyReadError::Closed) if buffer.is_empty() => return Ok(None),
+        Err(_) => return Err(HttpRequestError::InvalidRequest("Malformed response head".to_string())),
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..head_length]).to_string();
+    buffer.drain(..head_length);
+    let mut response = read_http_response(&head)?;
+
+    let mut reusable = match response.version {
+        HttpVersion::HTTP10 => has_connection_token(&response.headers, "keep-alive"),
+        _ => !has_connection_token(&response.headers, "close"),
+    };
+
+    let body = if head_only || !response.status_code.allows_body() {
+        None
+    } else if is_chunked(&response.headers) || find_header(&response.headers, "Content-Length").is_some() {
+        read_request_body(stream, &mut buffer, &response.headers, DEFAULT_MAX_BODY_SIZE)
+            .map_err(|_| HttpRequestError::InvalidRequest("Incomplete response body".to_string()))?
+    } else {
+        // without a length the body ends when the server closes the connection
+        reusable = false;
+        stream.read_to_end(&mut buffer).map_err(|e| io_error("Failed to read response", e))?;
+        Some(std::mem::take(&mut buffer)).filter(|body| !body.is_empty())
+    };
+
+    response.body = body.map(|body| match String::from_utf8(body) {
+        Ok(text) => HttpBody::Text(text),
+        Err(e) => HttpBody::Bytes(e.into_bytes()),
+    });
+
+    Ok(Some((response, reusable && buffer.is_empty())))
+}
+
+fn redirect_location(response: &HttpResponse) -> Option<String> {
+    let redirect = matches!(
+        response.status_code,
+        HttpStatusCode::MovedPermanently
+            | HttpStatusCode::Found
+            | HttpStatusCode::SeeOther
+            | HttpStatusCode::TemporaryRedirect
+            | HttpStatusCode::PermanentRedirect
+    );
+    match find_header(&response.headers, "Location") {
+        Some(KnownHeader::Location(location)) if redirect => Some(location.trim().to_string()),
+        _ => None,
+    }
+}
+
+// Location is an absolute URL, an absolute path on the same server or a path relative
+// to the directory of the current target
+fn resolve_location(location: &str, authority: String, target: &str) -> Result<(String, String), HttpRequestError> {
+    if location.contains("://") {
+        return split_url(location);
+    }
+    if location.starts_with('/') {
+        return Ok((authority, location.to_string()));
+    }
+
+    let path = target.split(['?', '#']).next().unwrap_or("/");
+    let directory = &path[..path.rfind('/').map_or(0, |pos| pos + 1)];
+    Ok((authority, format!("{}{}", directory, location)))
+}
+
+fn build_request(method: HttpMethod, authority: &str, target: &str, body: Option<String>) -> HttpRequest {
+    let mut headers = HashMap::new();
+    headers.insert("Host".to_string(), KnownHeader::Host(authority.to_string()));
+    if let Some(body) = &body {
+        headers.insert("Content-Length".to_string(), KnownHeader::ContentLength(body.len()));
+    }
+
+    HttpRequest {
+        method,
+        path: HttpPath::from_str(target),
+        version: HttpVersion::HTTP11,
+        headers,
+        body,
+        wildcard: None,
+        raw_request_line: String::new(),
+        request_id: None,
+        content_type_params: HashMap::new(),
+        raw_headers: Vec::new(),
+    }
+}
+
+// Splits "http://host:port/path?query" into the authority and the request target
+fn split_url(url: &str) -> Result<(String, String), HttpRequestError> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| HttpRequestError::InvalidRequest(format!("Unsupported URL: {}", url)))?;
+
+    let (authority, target) = match rest.find(['/', '?']) {
+        Some(pos) if rest[pos..].starts_with('?') => (&rest[..pos], format!("/{}", &rest[pos..])),
+        Some(pos) => (&rest[..pos], rest[pos..].to_string()),
+        None => (rest, "/".to_string()),
+    };
+
+    if authority.is_empty() {
+        return Err(HttpRequestError::InvalidRequest(format!("Missing host in URL: {}", url)));
+    }
+    Ok((authority.to_string(), target))
+}
+
+// Connections are pooled by host and port, with the port defaulting to 80
+fn pool_key(authority: &str) -> String {
+    match KnownHeader::Host(authority.to_string()).host_and_port() {
+        Some((_, Some(_))) => authority.to_string(),
+        _ => format!("{}:80", authority),
+    }
+}
+
+fn io_error(context: &str, error: io::Error) -> HttpRequestError {
+    HttpRequestError::InvalidRequest(format!("{}: {}", context, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::read_http_request_buf;
+    use crate::testing::{start_test_server, TestServer};
+    use std::io::BufReader;
+    use std::net::TcpListener;
+    use std::thread;
+
+    // Answers every request with the port of the connection it arrived on. With
+    // close_after_response the server hangs up after each answer without announcing it.
+    fn port_echo_server(close_after_response: bool) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = stream.unwrap();
+                thread::spawn(move || {
+                    let port = stream.peer_addr().unwrap().port().to_string();
+                    let mut writer = stream.try_clone().unwrap();
+                    let mut reader = BufReader::new(stream);
+                    while read_http_request_buf(&mut reader).is_ok() {
+                        let response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}", port.len(), port);
+                        if writer.write_all(response.as_bytes()).is_err() || close_after_response {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        addr
+    }
+
+    fn redirect(status_code: HttpStatusCode, location: &str) -> HttpResponse {
+        let mut response = HttpResponse::text(status_code, "");
+        response.headers.insert("Location".to_string(), KnownHeader::Location(location.to_string()));
+        response
+    }
+
+    fn redirect_server() -> TestServer {
+        start_test_server(|request: HttpRequest| match request.path.path.as_str() {
+            "/old" => redirect(HttpStatusCode::MovedPermanently, "/new"),
+            "/new" => HttpResponse::text(HttpStatusCode::OK, "moved here"),
+            "/submit" => redirect(HttpStatusCode::SeeOther, "result"),
+            "/keep" => redirect(HttpStatusCode::TemporaryRedirect, "/result"),
+            "/result" => HttpResponse::text(HttpStatusCode::OK, &format!("{:?}", request.method)),
+            _ => redirect(HttpStatusCode::Found, "/loop"),
+        })
+    }
+
+    fn body(response: &HttpResponse) -> String {
+        response.body.as_ref().and_then(HttpBody::as_text).unwrap_or("").to_string()
+    }
+
+    #[test]
+    fn split_url_into_authority_and_target() {
+        assert_eq!(split_url("http://example.com").unwrap(), ("example.com".to_string(), "/".to_string()));
+        assert_eq!(
+            split_url("http://127.0.0.1:8080/data?id=1").unwrap(),
+            ("127.0.0.1:8080".to_string(), "/data?id=1".to_string())
+        );
+        assert_eq!(split_url("http://example.com?id=1").unwrap(), ("example.com".to_string(), "/?id=1".to_string()));
+        assert!(split_url("https://example.com/").is_err());
+        assert!(split_url("http:///data").is_err());
+
+        assert_eq!(pool_key("example.com"), "example.com:80");
+        assert_eq!(pool_key("127.0.0.1:8080"), "127.0.0.1:8080");
+    }
+
+    #[test]
+    fn sequential_requests_reuse_the_connection() {
+        let addr = port_echo_server(false);
+        let mut client = HttpClient::new();
+
+        let first = client.get(&format!("http://{}/", addr)).unwrap();
+        assert_eq!(client.idle_connections(&addr), 1);
+        let second = client.get(&format!("http://{}/again", addr)).unwrap();
+
+        assert_eq!(body(&first), body(&second));
+        assert_eq!(client.idle_connections(&addr), 1);
+    }
+
+    #[test]
+    fn closed_connections_are_not_reused() {
+        let addr = port_echo_server(true);
+        let mut client = HttpClient::new();
+
+        let first = client.get(&format!("http://{}/", addr)).unwrap();
+        let second = client.get(&format!("http://{}/", addr)).unwrap();
+
+        assert_eq!(first.status_code, HttpStatusCode::OK);
+        assert_ne!(body(&first), body(&second));
+    }
+
+    #[test]
+    fn resolve_relative_locations() {
+        let authority = "example.com".to_string();
+        assert_eq!(
+            resolve_location("/b", authority.clone(), "/a/c?x=1").unwrap(),
+            ("example.com".to_string(), "/b".to_string())
+        );
+        assert_eq!(
+            resolve_location("b", authority.clone(), "/a/c?x=1").unwrap(),
+            ("example.com".to_string(), "/a/b".to_string())
+        );
+        assert_eq!(
+            resolve_location("http://other:8080/d", authority, "/").unwrap(),
+            ("other:8080".to_string(), "/d".to_string())
+        );
+    }
+
+    #[test]
+    fn follow_a_single_redirect() {
+        let server = redirect_server();
+
+        let response = HttpClient::new().get(&server.url("/old")).unwrap();
+        assert_eq!(response.status_code, HttpStatusCode::MovedPermanently);
+
+        let mut client = HttpClient::new().with_redirects(5);
+        let response = client.get(&server.url("/old")).unwrap();
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+        assert_eq!(body(&response), "moved here");
+    }
+
+    #[test]
+    fn redirect_method_handling() {
+        let server = redirect_server();
+        let mut client = HttpClient::new().with_redirects(5);
+
+        let response = client.request(HttpMethod::POST, &server.url("/submit"), Some("a=1".to_string())).unwrap();
+        assert_eq!(body(&response), "GET");
+
+        let response = client.request(HttpMethod::POST, &server.url("/keep"), Some("a=1".to_string())).unwrap();
+        assert_eq!(body(&response), "POST");
+    }
+
+    #[test]
+    fn redirect_loop_hits_the_limit() {
+        let server = redirect_server();
+        let mut client = HttpClient::new().with_redirects(3);
+
+        match client.get(&server.url("/loop")) {
+            Err(error) => assert_eq!(error, HttpRequestError::InvalidRequest("Too many redirects (limit 3)".to_string())),
+            Ok(response) => panic!("expected an error, got {:?}", response.status_code),
+        }
+    }
+}
@@ -0,0 +1,117 @@
+// Cookies set by a response, each one is written as its own Set-Cookie line
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    pub path: Option<String>,
+    pub domain: Option<String>,
+    pub max_age: Option<u64>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: Option<String>,
+}
+
+impl Cookie {
+    pub fn new(name: &str, value: &str) -> Cookie {
+        Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            ..Cookie::default()
+        }
+    }
+
+    // Reads a Set-Cookie value such as "id=abc; Path=/; HttpOnly". Attribute names are
+    // matched case-insensitively and unknown attributes are ignored, None without a name.
+    pub fn parse(value: &str) -> Option<Cookie> {
+        let mut parts = value.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut cookie = Cookie::new(name, value.trim());
+        for attribute in parts {
+            let (key, value) = match attribute.split_once('=') {
+                Some((key, value)) => (key.trim(), Some(value.trim())),
+                None => (attribute.trim(), None),
+            };
+
+            match (key.to_ascii_lowercase().as_str(), value) {
+                ("path", Some(path)) => cookie.path = Some(path.to_string()),
+                ("domain", Some(domain)) => cookie.domain = Some(domain.to_string()),
+                ("max-age", Some(seconds)) => cookie.max_age = seconds.parse().ok(),
+                ("samesite", Some(same_site)) => cookie.same_site = Some(same_site.to_string()),
+                ("secure", _) => cookie.secure = true,
+                ("httponly", _) => cookie.http_only = true,
+                _ => {}
+            }
+        }
+        Some(cookie)
+    }
+}
+
+// The Set-Cookie header value
+impl fmt::Display for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+
+        if let Some(path) = &self.path {
+            write!(f, "; Path={}", path)?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={}", domain)?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age)?;
+        }
+        if let Some(same_site) = &self.same_site {
+            write!(f, "; SameSite={}", same_site)?;
+        }
+        if self.secure {
+            write!(f, "; Secure")?;
+        }
+        if self.http_only {
+            write!(f, "; HttpOnly")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cookie_round_trip() {
+        let cookie = Cookie {
+            path: Some("/".to_string()),
+            max_age: Some(3600),
+            same_site: Some("Lax".to_string()),
+            http_only: true,
+            ..Cookie::new("session", "abc123")
+        };
+        let value = cookie.to_string();
+
+        assert_eq!(value, "session=abc123; Path=/; Max-Age=3600; SameSite=Lax; HttpOnly");
+        assert_eq!(Cookie::parse(&value), Some(cookie));
+        assert_eq!(Cookie::new("theme", "dark").to_string(), "theme=dark");
+    }
+
+    #[test]
+    fn parse_set_cookie_value() {
+        let cookie = Cookie::parse(" id = a=b ; domain=example.com; SECURE; Max-Age=soon; Extra=1").unwrap();
+
+        assert_eq!(cookie.name, "id");
+        assert_eq!(cookie.value, "a=b");
+        assert_eq!(cookie.domain, Some("example.com".to_string()));
+        assert!(cookie.secure);
+        assert!(!cookie.http_only);
+        assert_eq!(cookie.max_age, None);
+
+        assert_eq!(Cookie::parse("novalue"), None);
+        assert_eq!(Cookie::parse("=value"), None);
+    }
+}
@@ -0,0 +1,217 @@
+// HTTP dates use the IMF-fixdate format, e.g. "Sun, 06 Nov 1994 08:49:37 GMT"
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DAY_NAMES: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTH_NAMES: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+// An instant with the one second precision of the Date, Last-Modified, If-Modified-Since
+// and Expires headers. Parsing also accepts the obsolete RFC 850 and asctime formats,
+// it is always written back as an IMF-fixdate.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct HttpDate {
+    seconds: u64,
+}
+
+impl HttpDate {
+    pub fn from_unix_seconds(seconds: u64) -> HttpDate {
+        HttpDate { seconds }
+    }
+
+    pub fn now() -> HttpDate {
+        HttpDate::from(SystemTime::now())
+    }
+
+    pub fn unix_seconds(&self) -> u64 {
+        self.seconds
+    }
+
+    pub fn parse(value: &str) -> Option<HttpDate> {
+        let value = value.trim();
+        parse_http_date(value)
+            .or_else(|| parse_rfc850_date(value))
+            .or_else(|| parse_asctime_date(value))
+            .map(HttpDate::from_unix_seconds)
+    }
+}
+
+// Times before the epoch are clamped to it, sub-second parts are dropped
+impl From<SystemTime> for HttpDate {
+    fn from(time: SystemTime) -> HttpDate {
+        let seconds = time.duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+        HttpDate::from_unix_seconds(seconds)
+    }
+}
+
+impl fmt::Display for HttpDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&format_http_date(self.seconds))
+    }
+}
+
+// Formats seconds since the unix epoch as an IMF-fixdate
+pub(crate) fn format_http_date(seconds: u64) -> String {
+    let days = seconds / 86400;
+    let time = seconds % 86400;
+    let (year, month, day) = civil_from_days(days as i64);
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        DAY_NAMES[(days % 7) as usize],
+        day,
+        MONTH_NAMES[(month - 1) as usize],
+        year,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+// Parses an IMF-fixdate into seconds since the unix epoch, the day name is not checked
+pub(crate) fn parse_http_date(value: &str) -> Option<u64> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    if parts.len() != 6 || !parts[0].ends_with(',') || parts[5] != "GMT" {
+        return None;
+    }
+
+    if parts[1].len() != 2 {
+        return None;
+    }
+    let year: i64 = parts[3].parse().ok()?;
+    to_unix_seconds(year, parts[2], parts[1], parts[4])
+}
+
+// Obsolete RFC 850 format, e.g. "Sunday, 06-Nov-94 08:49:37 GMT"
+fn parse_rfc850_date(value: &str) -> Option<u64> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    if parts.len() != 4 || !parts[0].ends_with(',') || parts[3] != "GMT" {
+        return None;
+    }
+
+    let date: Vec<&str> = parts[1].split('-').collect();
+    if date.len() != 3 || date[0].len() != 2 || date[2].len() != 2 {
+        return None;
+    }
+
+    let year = expand_two_digit_year(date[2].parse().ok()?, current_year());
+    to_unix_seconds(year, date[1], date[0], parts[2])
+}
+
+// Obsolete asctime format, e.g. "Sun Nov  6 08:49:37 1994"
+fn parse_asctime_date(value: &str) -> Option<u64> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    if parts.len() != 5 || parts[2].len() > 2 {
+        return None;
+    }
+
+    let year: i64 = parts[4].parse().ok()?;
+    to_unix_seconds(year, parts[1], parts[2], parts[3])
+}
+
+// A two-digit year more than 50 years in the future means the most recent past year
+// ending in the same digits (RFC 7231 7.1.1.1)
+fn expand_two_digit_year(year: i64, current_year: i64) -> i64 {
+    let year = current_year - current_year % 100 + year;
+    if year > current_year + 50 { year - 100 } else { year }
+}
+
+fn current_year() -> i64 {
+    let days = HttpDate::now().unix_seconds() / 86400;
+    civil_from_days(days as i64).0
+}
+
+fn to_unix_seconds(year: i64, month: &str, day: &str, time: &str) -> Option<u64> {
+    let month = MONTH_NAMES.iter().position(|name| *name == month)? as u32 + 1;
+    let day: u32 = day.parse().ok()?;
+
+    let time: Vec<&str> = time.split(':').collect();
+    if time.len() != 3 {
+        return None;
+    }
+
+    let hour: u64 = time[0].parse().ok()?;
+    let minute: u64 = time[1].parse().ok()?;
+    let second: u64 = time[2].parse().ok()?;
+
+    if day == 0 || day > 31 || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+// Days since 1970-01-01 for a proleptic gregorian date (Howard Hinnant's algorithm)
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_and_parse_http_date() {
+        assert_eq!(format_http_date(784111777), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(format_http_date(0), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(784111777));
+        assert_eq!(parse_http_date("Tue, 29 Feb 2000 12:00:00 GMT"), Some(951825600));
+    }
+
+    #[test]
+    fn parse_http_date_rejects_other_formats() {
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 UTC"), None);
+        assert_eq!(parse_http_date(""), None);
+    }
+
+    #[test]
+    fn http_date_all_formats_are_the_same_instant() {
+        let expected = HttpDate::from_unix_seconds(784111777);
+
+        assert_eq!(HttpDate::parse("Sun, 06 Nov 1994 08:49:37 GMT"), Some(expected));
+        assert_eq!(HttpDate::parse("Sunday, 06-Nov-94 08:49:37 GMT"), Some(expected));
+        assert_eq!(HttpDate::parse("Sun Nov  6 08:49:37 1994"), Some(expected));
+
+        for value in ["Sunday, 06-Nov-94 08:49:37 GMT", "Sun Nov  6 08:49:37 1994"] {
+            assert_eq!(HttpDate::parse(value).unwrap().to_string(), "Sun, 06 Nov 1994 08:49:37 GMT");
+        }
+    }
+
+    #[test]
+    fn http_date_rejects_malformed_values() {
+        for value in ["", "yesterday", "Sun, 06 Nov 1994 25:49:37 GMT", "Sunday, 06-Nov-1994 08:49:37 GMT", "Sun Foo  6 08:49:37 1994"] {
+            assert_eq!(HttpDate::parse(value), None, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn two_digit_years() {
+        assert_eq!(expand_two_digit_year(94, 2026), 1994);
+        assert_eq!(expand_two_digit_year(30, 2026), 2030);
+        assert_eq!(expand_two_digit_year(76, 2026), 2076);
+        assert_eq!(expand_two_digit_year(77, 2026), 1977);
+    }
+}
@@ -0,0 +1,329 @@
+use std::{collections::HashMap, fs, path::Path, time::UNIX_EPOCH};
+
+use crate::date::HttpDate;
+use crate::http::{HttpContentType, HttpRequest, HttpResponse, HttpStatusCode, KnownHeader};
+
+// Serves a file below root. Behind a "/prefix/*" route the wildcard is used as the
+// file path, otherwise the normalized request path.
+pub fn serve_file(root: &Path, request: &HttpRequest) -> HttpResponse {
+    let relative = match &request.wildcard {
+        Some(wildcard) => wildcard.clone(),
+        None => request.path.normalized_path(),
+    };
+
+    // normalized paths never contain "..", but the wildcard can be set by hand
+    let relative = relative.trim_start_matches('/');
+    if relative.split('/').any(|segment| segment == "..") {
+        return status_response(request, HttpStatusCode::NotFound);
+    }
+
+    let file_path = root.join(relative);
+    let metadata = match fs::metadata(&file_path) {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return status_response(request, HttpStatusCode::NotFound),
+    };
+
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs());
+
+    let mut headers = HashMap::new();
+    if let Some(modified) = modified {
+        let modified = HttpDate::from_unix_seconds(modified);
+        headers.insert("Last-Modified".to_string(), KnownHeader::LastModified(modified));
+
+        if let Some(KnownHeader::IfModifiedSince(since)) = request.header("If-Modified-Since")
+            && modified <= *since
+        {
+            return HttpResponse {
+                version: request.version.clone(),
+                status_code: HttpStatusCode::NotModified,
+                headers,
+                cookies: Vec::new(),
+                body: None,
+            };
+        }
+    }
+
+    let contents = match fs::read(&file_path) {
+        Ok(contents) => contents,
+        Err(_) => return status_response(request, HttpStatusCode::InternalServerError),
+    };
+
+    let length = contents.len();
+    let range = match request.header("Range") {
+        Some(KnownHeader::Range(value)) => byte_range(value, length),
+        _ => ByteRange::Ignored,
+    };
+
+    let mut response = match range {
+        ByteRange::Ignored => HttpResponse::bytes(HttpStatusCode::OK, content_type_for(&file_path), contents),
+        ByteRange::Satisfiable(start, end) => {
+            let mut response = HttpResponse::bytes(
+                HttpStatusCode::PartialContent,
+                content_type_for(&file_path),
+                contents[start..=end].to_vec(),
+            );
+            response.headers.insert(
+                "Content-Range".to_string(),
+                KnownHeader::ContentRange(format!("bytes {}-{}/{}", start, end, length)),
+            );
+            response
+        }
+        ByteRange::Unsatisfiable => {
+            let mut response = status_response(request, HttpStatusCode::RangeNotSatisfiable);
+            response.headers.insert(
+                "Content-Range".to_string(),
+                KnownHeader::ContentRange(format!("bytes */{}", length)),
+            );
+            response
+        }
+    };
+    response.version = request.version.clone();
+    response.headers.extend(headers);
+    response.headers.insert("Accept-Ranges".to_string(), KnownHeader::AcceptRanges("bytes".to_string()));
+    response
+}
+
+enum ByteRange {
+    // no usable range, the whole file is sent
+    Ignored,
+    // first and last byte, both inclusive
+    Satisfiable(usize, usize),
+    Unsatisfiable,
+}
+
+// Reads a Range header for a file of length bytes. Only a single "bytes=" range is
+// served, malformed values and multiple ranges fall back to the whole file.
+fn byte_range(value: &str, length: usize) -> ByteRange {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return ByteRange::Ignored;
+    };
+    let Some((first, last)) = spec.trim().split_once('-') else {
+        return ByteRange::Ignored;
+    };
+    if spec.contains(',') {
+        return ByteRange::Ignored;
+    }
+
+    let (first, last) = (first.trim(), last.trim());
+    let last_byte = length.saturating_sub(1);
+    let (start, end) = if first.is_empty() {
+        // "bytes=-500" is the last 500 bytes
+        match last.parse::<usize>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(suffix) => (length.saturating_sub(suffix), last_byte),
+            Err(_) => return ByteRange::Ignored,
+        }
+    } else {
+        match (first.parse::<usize>(), last.parse::<usize>()) {
+            (Ok(start), _) if last.is_empty() => (start, last_byte),
+            (Ok(start), Ok(end)) if start <= end => (start, end.min(last_byte)),
+            _ => return ByteRange::Ignored,
+        }
+    };
+
+    if start >= length {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Satisfiable(start, end)
+}
+
+fn content_type_for(path: &Path) -> HttpContentType {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase());
+
+    match extension.as_deref() {
+        Some("html") | Some("htm") => HttpContentType::TextHtml,
+        Some("json") => HttpContentType::ApplicationJson,
+        Some("xml") => HttpContentType::ApplicationXml,
+        Some("txt") => HttpContentType::TextPlain,
+        Some("css") => HttpContentType::Other("text/css".to_string()),
+        Some("js") => HttpContentType::Other("application/javascript".to_string()),
+        Some("png") => HttpContentType::Other("image/png".to_string()),
+        Some("jpg") | Some("jpeg") => HttpContentType::Other("image/jpeg".to_string()),
+        Some("gif") => HttpContentType::Other("image/gif".to_string()),
+        Some("svg") => HttpContentType::Other("image/svg+xml".to_string()),
+        Some("ico") => HttpContentType::Other("image/x-icon".to_string()),
+        _ => HttpContentType::Other("application/octet-stream".to_string()),
+    }
+}
+
+fn status_response(request: &HttpRequest, status_code: HttpStatusCode) -> HttpResponse {
+    HttpResponse {
+        version: request.version.clone(),
+        status_code,
+        headers: HashMap::new(),
+        cookies: Vec::new(),
+        body: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{read_http_request, HttpBody};
+    use std::path::PathBuf;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("parsing-files-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("index.html"), "<h1>Hello</h1>").unwrap();
+        root
+    }
+
+    fn file_mtime(path: &Path) -> u64 {
+        fs::metadata(path)
+            .unwrap()
+            .modified()
+            .unwrap()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn get_range(path: &str, range: &str) -> HttpRequest {
+        read_http_request(format!("GET {} HTTP/1.1\r\nHost: localhost\r\nRange: {}\r\n\r\n", path, range).as_str()).unwrap()
+    }
+
+    fn get(path: &str, if_modified_since: Option<String>) -> HttpRequest {
+        let mut request_str = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n", path);
+        if let Some(since) = if_modified_since {
+            request_str.push_str(&format!("If-Modified-Since: {}\r\n", since));
+        }
+        request_str.push_str("\r\n");
+        read_http_request(request_str.as_str()).unwrap()
+    }
+
+    #[test]
+    fn serve_file_not_modified() {
+        let root = temp_root("unchanged");
+        let modified = file_mtime(&root.join("index.html"));
+
+        let response = serve_file(&root, &get("/index.html", Some(HttpDate::from_unix_seconds(modified).to_string())));
+        assert_eq!(response.status_code, HttpStatusCode::NotModified);
+        assert_eq!(response.body, None);
+
+        let response = serve_file(&root, &get("/index.html", Some(HttpDate::from_unix_seconds(modified + 60).to_string())));
+        assert_eq!(response.status_code, HttpStatusCode::NotModified);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn serve_file_modified_since() {
+        let root = temp_root("modified");
+        let modified = file_mtime(&root.join("index.html"));
+
+        let response = serve_file(&root, &get("/index.html", Some(HttpDate::from_unix_seconds(modified - 60).to_string())));
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+        assert_eq!(response.body, Some(HttpBody::Bytes(b"<h1>Hello</h1>".to_vec())));
+        assert_eq!(
+            response.headers.get("Last-Modified"),
+            Some(&KnownHeader::LastModified(HttpDate::from_unix_seconds(modified)))
+        );
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some(&KnownHeader::ContentType(HttpContentType::TextHtml))
+        );
+
+        let response = serve_file(&root, &get("/index.html", Some("not a date".to_string())));
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn serve_binary_file() {
+        let root = temp_root("binary");
+        let png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0xff];
+        fs::write(root.join("logo.png"), &png).unwrap();
+
+        let response = serve_file(&root, &get("/logo.png", None));
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+        assert_eq!(response.body, Some(HttpBody::Bytes(png)));
+        assert_eq!(response.headers.get("Content-Length"), Some(&KnownHeader::ContentLength(9)));
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some(&KnownHeader::ContentType(HttpContentType::Other("image/png".to_string())))
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn serve_file_advertises_byte_ranges() {
+        let root = temp_root("accept-ranges");
+
+        let response = serve_file(&root, &get("/index.html", None));
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+        assert_eq!(
+            response.headers.get("Accept-Ranges"),
+            Some(&KnownHeader::AcceptRanges("bytes".to_string()))
+        );
+        assert_eq!(response.headers.get("Content-Range"), None);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn serve_file_byte_range() {
+        let root = temp_root("range");
+
+        // the file is "<h1>Hello</h1>", 14 bytes
+        let cases = [
+            ("bytes=4-8", "Hello", "bytes 4-8/14"),
+            ("bytes=9-", "</h1>", "bytes 9-13/14"),
+            ("bytes=-5", "</h1>", "bytes 9-13/14"),
+            ("bytes=10-100", "/h1>", "bytes 10-13/14"),
+            ("bytes=-100", "<h1>Hello</h1>", "bytes 0-13/14"),
+        ];
+        for (range, body, content_range) in cases {
+            let response = serve_file(&root, &get_range("/index.html", range));
+            assert_eq!(response.status_code, HttpStatusCode::PartialContent, "{}", range);
+            assert_eq!(response.body, Some(HttpBody::Bytes(body.as_bytes().to_vec())), "{}", range);
+            assert_eq!(response.headers.get("Content-Length"), Some(&KnownHeader::ContentLength(body.len())));
+            assert_eq!(
+                response.headers.get("Content-Range"),
+                Some(&KnownHeader::ContentRange(content_range.to_string()))
+            );
+        }
+
+        for range in ["bytes=14-", "bytes=-0"] {
+            let response = serve_file(&root, &get_range("/index.html", range));
+            assert_eq!(response.status_code, HttpStatusCode::RangeNotSatisfiable, "{}", range);
+            assert_eq!(
+                response.headers.get("Content-Range"),
+                Some(&KnownHeader::ContentRange("bytes */14".to_string()))
+            );
+        }
+
+        // ranges that are not understood are ignored and the whole file is sent
+        for range in ["items=0-1", "bytes=5-2", "bytes=0-1,4-5", "bytes=a-b"] {
+            let response = serve_file(&root, &get_range("/index.html", range));
+            assert_eq!(response.status_code, HttpStatusCode::OK, "{}", range);
+            assert_eq!(response.body, Some(HttpBody::Bytes(b"<h1>Hello</h1>".to_vec())));
+        }
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn serve_file_missing() {
+        let root = temp_root("missing");
+
+        let response = serve_file(&root, &get("/missing.html", None));
+        assert_eq!(response.status_code, HttpStatusCode::NotFound);
+
+        let mut request = get("/index.html", None);
+        request.wildcard = Some("../index.html".to_string());
+        let response = serve_file(&root, &request);
+        assert_eq!(response.status_code, HttpStatusCode::NotFound);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,248 @@
+// gzip (RFC 1952) around a single DEFLATE block (RFC 1951) with the fixed Huffman codes
+// and a greedy LZ77 match search. Not as small as zlib's output, but no tables have to be sent.
+
+const WINDOW_SIZE: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: u32 = 15;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+pub(crate) fn gzip_compress(data: &[u8]) -> Vec<u8> {
+    // magic, deflate, no flags, no mtime, no extra flags, unknown OS
+    let mut output = vec![0x1f, 0x8b, 0x08, 0x00, 0, 0, 0, 0, 0x00, 0xff];
+    output.extend(deflate(data));
+    output.extend_from_slice(&crc32(data).to_le_bytes());
+    output.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    output
+}
+
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    // BFINAL = 1, BTYPE = 01 (fixed Huffman codes)
+    writer.write_bits(1, 1);
+    writer.write_bits(1, 2);
+
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut i = 0;
+
+    while i < data.len() {
+        let (length, distance) = find_match(data, i, &head);
+
+        if length >= MIN_MATCH {
+            write_length(&mut writer, length);
+            write_distance(&mut writer, distance);
+            for position in i..i + length {
+                insert_hash(data, position, &mut head);
+            }
+            i += length;
+        } else {
+            write_literal(&mut writer, data[i] as u16);
+            insert_hash(data, i, &mut head);
+            i += 1;
+        }
+    }
+
+    write_literal(&mut writer, 256);
+    writer.finish()
+}
+
+fn hash(data: &[u8], position: usize) -> Option<usize> {
+    let bytes = data.get(position..position + MIN_MATCH)?;
+    let value = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+    Some((value.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize)
+}
+
+fn insert_hash(data: &[u8], position: usize, head: &mut [usize]) {
+    if let Some(hash) = hash(data, position) {
+        head[hash] = position;
+    }
+}
+
+// Only the most recent position per hash is tried, which keeps the search cheap
+fn find_match(data: &[u8], position: usize, head: &[usize]) -> (usize, usize) {
+    let candidate = match hash(data, position) {
+        Some(hash) => head[hash],
+        None => return (0, 0),
+    };
+
+    if candidate == usize::MAX || position - candidate > WINDOW_SIZE {
+        return (0, 0);
+    }
+
+    let max_length = MAX_MATCH.min(data.len() - position);
+    let length = (0..max_length)
+        .take_while(|&offset| data[candidate + offset] == data[position + offset])
+        .count();
+
+    (length, position - candidate)
+}
+
+fn write_literal(writer: &mut BitWriter, symbol: u16) {
+    let (code, bits) = match symbol {
+        0..=143 => (0x30 + symbol, 8),
+        144..=255 => (0x190 + symbol - 144, 9),
+        256..=279 => (symbol - 256, 7),
+        _ => (0xc0 + symbol - 280, 8),
+    };
+    writer.write_code(code as u32, bits);
+}
+
+fn write_length(writer: &mut BitWriter, length: usize) {
+    let index = LENGTH_BASE.iter().rposition(|&base| base as usize <= length).unwrap();
+    write_literal(writer, 257 + index as u16);
+    writer.write_bits((length - LENGTH_BASE[index] as usize) as u32, LENGTH_EXTRA[index] as u32);
+}
+
+fn write_distance(writer: &mut BitWriter, distance: usize) {
+    let index = DISTANCE_BASE.iter().rposition(|&base| base as usize <= distance).unwrap();
+    writer.write_code(index as u32, 5);
+    writer.write_bits((distance - DISTANCE_BASE[index] as usize) as u32, DISTANCE_EXTRA[index] as u32);
+}
+
+// DEFLATE packs bits starting at the least significant bit of each byte, Huffman codes
+// are stored most significant bit first while extra bits are stored as plain numbers
+#[derive(Default)]
+struct BitWriter {
+    output: Vec<u8>,
+    buffer: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    fn write_bits(&mut self, value: u32, bits: u32) {
+        for bit in 0..bits {
+            self.buffer |= ((value >> bit) & 1) << self.count;
+            self.count += 1;
+            if self.count == 8 {
+                self.output.push(self.buffer as u8);
+                self.buffer = 0;
+                self.count = 0;
+            }
+        }
+    }
+
+    fn write_code(&mut self, code: u32, bits: u32) {
+        let reversed = code.reverse_bits() >> (32 - bits);
+        self.write_bits(reversed, bits);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.output.push(self.buffer as u8);
+        }
+        self.output
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    struct BitReader<'a> {
+        data: &'a [u8],
+        position: usize,
+    }
+
+    impl BitReader<'_> {
+        fn bit(&mut self) -> u32 {
+            let bit = (self.data[self.position / 8] >> (self.position % 8)) & 1;
+            self.position += 1;
+            bit as u32
+        }
+
+        fn bits(&mut self, count: u32) -> u32 {
+            (0..count).fold(0, |value, bit| value | self.bit() << bit)
+        }
+
+        fn code(&mut self, count: u32) -> u32 {
+            (0..count).fold(0, |code, _| code << 1 | self.bit())
+        }
+
+        fn literal(&mut self) -> u32 {
+            let code = self.code(7);
+            if code <= 0x17 {
+                return 256 + code;
+            }
+            let code = code << 1 | self.bit();
+            match code {
+                0x30..=0xbf => code - 0x30,
+                0xc0..=0xc7 => 280 + code - 0xc0,
+                _ => 144 + (code << 1 | self.bit()) - 0x190,
+            }
+        }
+    }
+
+    // Inflates the fixed Huffman block written above and checks the gzip trailer
+    pub(crate) fn gunzip(data: &[u8]) -> Vec<u8> {
+        assert_eq!(&data[..4], &[0x1f, 0x8b, 0x08, 0x00]);
+        let mut reader = BitReader {
+            data: &data[10..data.len() - 8],
+            position: 0,
+        };
+        assert_eq!(reader.bits(3), 0b011);
+
+        let mut output: Vec<u8> = Vec::new();
+        loop {
+            let symbol = reader.literal();
+            match symbol {
+                0..=255 => output.push(symbol as u8),
+                256 => break,
+                _ => {
+                    let index = (symbol - 257) as usize;
+                    let length = LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index] as u32) as usize;
+                    let index = reader.code(5) as usize;
+                    let distance = DISTANCE_BASE[index] as usize + reader.bits(DISTANCE_EXTRA[index] as u32) as usize;
+
+                    for _ in 0..length {
+                        output.push(output[output.len() - distance]);
+                    }
+                }
+            }
+        }
+
+        let trailer = &data[data.len() - 8..];
+        assert_eq!(trailer[..4], crc32(&output).to_le_bytes());
+        assert_eq!(trailer[4..], (output.len() as u32).to_le_bytes());
+        output
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn gzip_round_trip() {
+        let text = "The quick brown fox jumps over the lazy dog. ".repeat(200);
+        let compressed = gzip_compress(text.as_bytes());
+        assert!(compressed.len() < text.len() / 10);
+        assert_eq!(gunzip(&compressed), text.as_bytes());
+
+        let binary: Vec<u8> = (0..5000u32).map(|i| (i * 7919 % 256) as u8).collect();
+        assert_eq!(gunzip(&gzip_compress(&binary)), binary);
+
+        assert_eq!(gunzip(&gzip_compress(b"")), b"");
+        assert_eq!(gunzip(&gzip_compress(b"a")), b"a");
+    }
+}
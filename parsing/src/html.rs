@@ -0,0 +1,40 @@
+// Escapes the characters that are significant in HTML text and attribute values,
+// anything taken from a request should pass through this before landing in a page
+pub fn html_escape(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            c => output.push(c),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_escape_special_characters() {
+        assert_eq!(html_escape("&"), "&amp;");
+        assert_eq!(html_escape("<"), "&lt;");
+        assert_eq!(html_escape(">"), "&gt;");
+        assert_eq!(html_escape("\""), "&quot;");
+        assert_eq!(html_escape("'"), "&#39;");
+        assert_eq!(
+            html_escape("<script>alert('x & y')</script>"),
+            "&lt;script&gt;alert(&#39;x &amp; y&#39;)&lt;/script&gt;"
+        );
+    }
+
+    #[test]
+    fn html_escape_plain_text() {
+        assert_eq!(html_escape("John Doe, caf\u{e9}"), "John Doe, caf\u{e9}");
+        assert_eq!(html_escape(""), "");
+    }
+}
@@ -1,8 +1,22 @@
 use std::{
     collections::HashMap,
-    io::{Read, Write},
+    io::{self, BufRead, Read, Write},
+    net::{SocketAddr, TcpListener, ToSocketAddrs},
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
+use crate::cookie::Cookie;
+use crate::date::HttpDate;
+use crate::gzip::gzip_compress;
+use crate::query::FromQuery;
+use crate::urlencoding::decode_query;
+use crate::json::{parse_json, write_json, FromJson, JsonType, ParserError};
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum HttpMethod {
     GET,
@@ -14,6 +28,7 @@ pub enum HttpMethod {
     PATCH,
     TRACE,
     CONNECT,
+    Other(String),
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
@@ -56,18 +71,32 @@ pub enum HttpContentType {
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum HttpStatusCode {
+    Continue = 100,
+    SwitchingProtocols = 101,
     OK = 200,
     Created = 201,
     Accepted = 202,
     NoContent = 204,
+    PartialContent = 206,
     MovedPermanently = 301,
     Found = 302,
+    SeeOther = 303,
     NotModified = 304,
+    TemporaryRedirect = 307,
+    PermanentRedirect = 308,
     BadRequest = 400,
     Unauthorized = 401,
     Forbidden = 403,
     NotFound = 404,
     MethodNotAllowed = 405,
+    NotAcceptable = 406,
+    RequestTimeout = 408,
+    PreconditionFailed = 412,
+    PayloadTooLarge = 413,
+    UriTooLong = 414,
+    UnsupportedMediaType = 415,
+    RangeNotSatisfiable = 416,
+    RequestHeaderFieldsTooLarge = 431,
     InternalServerError = 500,
     NotImplemented = 501,
     BadGateway = 502,
@@ -77,18 +106,32 @@ pub enum HttpStatusCode {
 impl HttpStatusCode {
     fn to_str(&self) -> &str {
         match self {
+            HttpStatusCode::Continue => "100",
+            HttpStatusCode::SwitchingProtocols => "101",
             HttpStatusCode::OK => "200",
             HttpStatusCode::Created => "201",
             HttpStatusCode::Accepted => "202",
             HttpStatusCode::NoContent => "204",
+            HttpStatusCode::PartialContent => "206",
             HttpStatusCode::MovedPermanently => "301",
             HttpStatusCode::Found => "302",
+            HttpStatusCode::SeeOther => "303",
             HttpStatusCode::NotModified => "304",
+            HttpStatusCode::TemporaryRedirect => "307",
+            HttpStatusCode::PermanentRedirect => "308",
             HttpStatusCode::BadRequest => "400",
             HttpStatusCode::Unauthorized => "401",
             HttpStatusCode::Forbidden => "403",
             HttpStatusCode::NotFound => "404",
             HttpStatusCode::MethodNotAllowed => "405",
+            HttpStatusCode::NotAcceptable => "406",
+            HttpStatusCode::RequestTimeout => "408",
+            HttpStatusCode::PreconditionFailed => "412",
+            HttpStatusCode::PayloadTooLarge => "413",
+            HttpStatusCode::UriTooLong => "414",
+            HttpStatusCode::UnsupportedMediaType => "415",
+            HttpStatusCode::RangeNotSatisfiable => "416",
+            HttpStatusCode::RequestHeaderFieldsTooLarge => "431",
             HttpStatusCode::InternalServerError => "500",
             HttpStatusCode::NotImplemented => "501",
             HttpStatusCode::BadGateway => "502",
@@ -96,20 +139,45 @@ impl HttpStatusCode {
         }
     }
 
+    // 1xx, 204 and 304 responses never carry a body or a Content-Length (RFC 7230 3.3)
+    pub(crate) fn allows_body(&self) -> bool {
+        !matches!(
+            self,
+            HttpStatusCode::Continue
+                | HttpStatusCode::SwitchingProtocols
+                | HttpStatusCode::NoContent
+                | HttpStatusCode::NotModified
+        )
+    }
+
     fn from_str(code: &str) -> Result<HttpStatusCode, HttpRequestError> {
         match code {
+            "100" => Ok(HttpStatusCode::Continue),
+            "101" => Ok(HttpStatusCode::SwitchingProtocols),
             "200" => Ok(HttpStatusCode::OK),
             "201" => Ok(HttpStatusCode::Created),
             "202" => Ok(HttpStatusCode::Accepted),
             "204" => Ok(HttpStatusCode::NoContent),
+            "206" => Ok(HttpStatusCode::PartialContent),
             "301" => Ok(HttpStatusCode::MovedPermanently),
             "302" => Ok(HttpStatusCode::Found),
+            "303" => Ok(HttpStatusCode::SeeOther),
             "304" => Ok(HttpStatusCode::NotModified),
+            "307" => Ok(HttpStatusCode::TemporaryRedirect),
+            "308" => Ok(HttpStatusCode::PermanentRedirect),
             "400" => Ok(HttpStatusCode::BadRequest),
             "401" => Ok(HttpStatusCode::Unauthorized),
             "403" => Ok(HttpStatusCode::Forbidden),
             "404" => Ok(HttpStatusCode::NotFound),
             "405" => Ok(HttpStatusCode::MethodNotAllowed),
+            "406" => Ok(HttpStatusCode::NotAcceptable),
+            "408" => Ok(HttpStatusCode::RequestTimeout),
+            "412" => Ok(HttpStatusCode::PreconditionFailed),
+            "413" => Ok(HttpStatusCode::PayloadTooLarge),
+            "414" => Ok(HttpStatusCode::UriTooLong),
+            "415" => Ok(HttpStatusCode::UnsupportedMediaType),
+            "416" => Ok(HttpStatusCode::RangeNotSatisfiable),
+            "431" => Ok(HttpStatusCode::RequestHeaderFieldsTooLarge),
             "500" => Ok(HttpStatusCode::InternalServerError),
             "501" => Ok(HttpStatusCode::NotImplemented),
             "502" => Ok(HttpStatusCode::BadGateway),
@@ -125,18 +193,32 @@ impl HttpStatusCode {
 impl HttpStatusCode {
     fn status_text(&self) -> &str {
         match self {
+            HttpStatusCode::Continue => "Continue",
+            HttpStatusCode::SwitchingProtocols => "Switching Protocols",
             HttpStatusCode::OK => "OK",
             HttpStatusCode::Created => "Created",
             HttpStatusCode::Accepted => "Accepted",
             HttpStatusCode::NoContent => "No Content",
+            HttpStatusCode::PartialContent => "Partial Content",
             HttpStatusCode::MovedPermanently => "Moved Permanently",
             HttpStatusCode::Found => "Found",
+            HttpStatusCode::SeeOther => "See Other",
             HttpStatusCode::NotModified => "Not Modified",
+            HttpStatusCode::TemporaryRedirect => "Temporary Redirect",
+            HttpStatusCode::PermanentRedirect => "Permanent Redirect",
             HttpStatusCode::BadRequest => "Bad Request",
             HttpStatusCode::Unauthorized => "Unauthorized",
             HttpStatusCode::Forbidden => "Forbidden",
             HttpStatusCode::NotFound => "Not Found",
             HttpStatusCode::MethodNotAllowed => "Method Not Allowed",
+            HttpStatusCode::NotAcceptable => "Not Acceptable",
+            HttpStatusCode::RequestTimeout => "Request Timeout",
+            HttpStatusCode::PreconditionFailed => "Precondition Failed",
+            HttpStatusCode::PayloadTooLarge => "Payload Too Large",
+            HttpStatusCode::UriTooLong => "URI Too Long",
+            HttpStatusCode::UnsupportedMediaType => "Unsupported Media Type",
+            HttpStatusCode::RangeNotSatisfiable => "Range Not Satisfiable",
+            HttpStatusCode::RequestHeaderFieldsTooLarge => "Request Header Fields Too Large",
             HttpStatusCode::InternalServerError => "Internal Server Error",
             HttpStatusCode::NotImplemented => "Not Implemented",
             HttpStatusCode::BadGateway => "Bad Gateway",
@@ -157,6 +239,23 @@ pub enum KnownHeader {
     Connection(String),
     Cookie(String),
     Referer(String),
+    TransferEncoding(String),
+    ContentEncoding(String),
+    Origin(String),
+    Date(HttpDate),
+    Expires(HttpDate),
+    LastModified(HttpDate),
+    IfModifiedSince(HttpDate),
+    Allow(String),
+    RequestId(String),
+    IfMatch(String),
+    Upgrade(String),
+    SecWebSocketKey(String),
+    SecWebSocketAccept(String),
+    Location(String),
+    AcceptRanges(String),
+    Range(String),
+    ContentRange(String),
     Other(String), // (header name, header value)
 }
 
@@ -179,13 +278,199 @@ impl KnownHeader {
             "connection" => KnownHeader::Connection(header_value.to_string()),
             "cookie" => KnownHeader::Cookie(header_value.to_string()),
             "referer" => KnownHeader::Referer(header_value.to_string()),
+            "transfer-encoding" => KnownHeader::TransferEncoding(header_value.to_string()),
+            "content-encoding" => KnownHeader::ContentEncoding(header_value.to_string()),
+            "origin" => KnownHeader::Origin(header_value.to_string()),
+            // dates that don't parse, like "Expires: 0", are kept as they are
+            "date" => date_header(header_value, KnownHeader::Date),
+            "expires" => date_header(header_value, KnownHeader::Expires),
+            "last-modified" => date_header(header_value, KnownHeader::LastModified),
+            "if-modified-since" => date_header(header_value, KnownHeader::IfModifiedSince),
+            "allow" => KnownHeader::Allow(header_value.to_string()),
+            "x-request-id" => KnownHeader::RequestId(header_value.to_string()),
+            "if-match" => KnownHeader::IfMatch(header_value.to_string()),
+            "upgrade" => KnownHeader::Upgrade(header_value.to_string()),
+            "sec-websocket-key" => KnownHeader::SecWebSocketKey(header_value.to_string()),
+            "sec-websocket-accept" => KnownHeader::SecWebSocketAccept(header_value.to_string()),
+            "location" => KnownHeader::Location(header_value.to_string()),
+            "accept-ranges" => KnownHeader::AcceptRanges(header_value.to_string()),
+            "range" => KnownHeader::Range(header_value.to_string()),
+            "content-range" => KnownHeader::ContentRange(header_value.to_string()),
             _ => KnownHeader::Other(header_value.to_string()),
         }
     }
+
+    fn to_value(&self) -> String {
+        match self {
+            KnownHeader::ContentType(ct) => ct.to_str().to_string(),
+            KnownHeader::ContentLength(len) => len.to_string(),
+            KnownHeader::UserAgent(ua) => ua.clone(),
+            KnownHeader::Accept(acc) => acc.clone(),
+            KnownHeader::Host(host) => host.clone(),
+            KnownHeader::Authorization(auth) => auth.clone(),
+            KnownHeader::CacheControl(cc) => cc.clone(),
+            KnownHeader::Connection(conn) => conn.clone(),
+            KnownHeader::Cookie(cookie) => cookie.clone(),
+            KnownHeader::Referer(referer) => referer.clone(),
+            KnownHeader::TransferEncoding(te) => te.clone(),
+            KnownHeader::ContentEncoding(ce) => ce.clone(),
+            KnownHeader::Origin(origin) => origin.clone(),
+            KnownHeader::Date(date) => date.to_string(),
+            KnownHeader::Expires(expires) => expires.to_string(),
+            KnownHeader::LastModified(last_modified) => last_modified.to_string(),
+            KnownHeader::IfModifiedSince(since) => since.to_string(),
+            KnownHeader::Allow(allow) => allow.clone(),
+            KnownHeader::RequestId(id) => id.clone(),
+            KnownHeader::IfMatch(tags) => tags.clone(),
+            KnownHeader::Upgrade(protocols) => protocols.clone(),
+            KnownHeader::SecWebSocketKey(key) => key.clone(),
+            KnownHeader::SecWebSocketAccept(accept) => accept.clone(),
+            KnownHeader::Location(location) => location.clone(),
+            KnownHeader::AcceptRanges(ranges) => ranges.clone(),
+            KnownHeader::Range(range) => range.clone(),
+            KnownHeader::ContentRange(range) => range.clone(),
+            KnownHeader::Other(value) => value.clone(),
+        }
+    }
+
+    // Splits a Host header value into host and port, e.g. "example.com:8080" or "[::1]:8080".
+    // Returns None for any other header; the port is None when the value doesn't carry one.
+    pub fn host_and_port(&self) -> Option<(String, Option<u16>)> {
+        match self {
+            KnownHeader::Host(value) => Some(split_host_port(value)),
+            _ => None,
+        }
+    }
+
+    // The parsed directives of a Cache-Control header, None for any other header
+    pub fn cache_control(&self) -> Option<CacheControl> {
+        match self {
+            KnownHeader::CacheControl(value) => Some(CacheControl::parse(value)),
+            _ => None,
+        }
+    }
+}
+
+// Directives of a Cache-Control header. Unknown directives are ignored and directive
+// names are case-insensitive; a max-age that isn't a number is treated as missing.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct CacheControl {
+    pub max_age: Option<u64>,
+    pub s_maxage: Option<u64>,
+    pub no_cache: bool,
+    pub no_store: bool,
+    pub no_transform: bool,
+    pub must_revalidate: bool,
+    pub public: bool,
+    pub private: bool,
+    pub immutable: bool,
+}
+
+impl CacheControl {
+    pub fn parse(value: &str) -> CacheControl {
+        let mut cache_control = CacheControl::default();
+
+        for directive in value.split(',') {
+            let (name, argument) = match directive.split_once('=') {
+                Some((name, argument)) => (name.trim(), Some(argument.trim().trim_matches('"'))),
+                None => (directive.trim(), None),
+            };
+            let seconds = argument.and_then(|argument| argument.parse::<u64>().ok());
+
+            match name.to_ascii_lowercase().as_str() {
+                "max-age" => cache_control.max_age = seconds,
+                "s-maxage" => cache_control.s_maxage = seconds,
+                "no-cache" => cache_control.no_cache = true,
+                "no-store" => cache_control.no_store = true,
+                "no-transform" => cache_control.no_transform = true,
+                "must-revalidate" => cache_control.must_revalidate = true,
+                "public" => cache_control.public = true,
+                "private" => cache_control.private = true,
+                "immutable" => cache_control.immutable = true,
+                _ => {}
+            }
+        }
+
+        cache_control
+    }
+}
+
+// Headers whose value is a comma-separated list, where whitespace carries no meaning
+const LIST_HEADERS: [&str; 11] = [
+    "Accept",
+    "Accept-Charset",
+    "Accept-Encoding",
+    "Accept-Language",
+    "Allow",
+    "Cache-Control",
+    "Connection",
+    "If-Match",
+    "Transfer-Encoding",
+    "Upgrade",
+    "Vary",
+];
+
+fn collapse_whitespace(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+    let mut in_quotes = false;
+    let mut escaped = false;
+    let mut pending_space = false;
+
+    for c in value.trim().chars() {
+        if !in_quotes && c.is_whitespace() {
+            pending_space = true;
+            continue;
+        }
+        if pending_space {
+            output.push(' ');
+            pending_space = false;
+        }
+
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            _ => {}
+        }
+        output.push(c);
+    }
+
+    output
+}
+
+fn date_header(value: &str, header: fn(HttpDate) -> KnownHeader) -> KnownHeader {
+    match HttpDate::parse(value) {
+        Some(date) => header(date),
+        None => KnownHeader::Other(value.to_string()),
+    }
+}
+
+fn split_host_port(value: &str) -> (String, Option<u16>) {
+    let value = value.trim();
+
+    if let Some(colon_index) = value.rfind(':') {
+        let host = &value[..colon_index];
+        // a bare IPv6 literal has colons of its own, only a bracketed one can carry a port
+        if (!host.contains(':') || (host.starts_with('[') && host.ends_with(']')))
+            && let Ok(port) = value[colon_index + 1..].parse::<u16>()
+        {
+            return (strip_ipv6_brackets(host).to_string(), Some(port));
+        }
+    }
+
+    (strip_ipv6_brackets(value).to_string(), None)
+}
+
+fn strip_ipv6_brackets(host: &str) -> &str {
+    host.strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host)
 }
 
 pub struct HttpPath {
     pub full_path: String,
+    pub scheme: Option<String>,
+    pub authority: Option<String>,
     pub path: String,
     pub query: Option<HashMap<String, String>>,
     pub fragment: Option<String>,
@@ -193,40 +478,124 @@ pub struct HttpPath {
 
 impl HttpPath {
     pub fn from_str(path: &str) -> HttpPath {
-        let mut full_path = path.to_string();
-        let mut path_only = path.to_string();
+        let mut rest = path;
+        let mut scheme: Option<String> = None;
+        let mut authority: Option<String> = None;
         let mut query: Option<HashMap<String, String>> = None;
         let mut fragment: Option<String> = None;
 
-        if let Some(hash_index) = full_path.find('#') {
-            fragment = Some(full_path[hash_index + 1..].to_string());
-            full_path = full_path[..hash_index].to_string();
+        // absolute-form (e.g. "http://example.com/path?x=1") as sent to proxies
+        if let Some(scheme_end) = rest.find("://") {
+            let candidate = &rest[..scheme_end];
+            if !candidate.is_empty() && candidate.chars().all(is_scheme_char) {
+                scheme = Some(candidate.to_lowercase());
+
+                let after_scheme = &rest[scheme_end + 3..];
+                let authority_end = after_scheme
+                    .find(['/', '?', '#'])
+                    .unwrap_or(after_scheme.len());
+                authority = Some(after_scheme[..authority_end].to_string());
+                rest = &after_scheme[authority_end..];
+            }
+        }
+
+        if let Some(hash_index) = rest.find('#') {
+            fragment = Some(rest[hash_index + 1..].to_string());
+            rest = &rest[..hash_index];
         }
 
-        if let Some(question_index) = full_path.find('?') {
-            let query_str = &full_path[question_index + 1..];
-            path_only = full_path[..question_index].to_string();
+        let mut path_only = match rest.find('?') {
+            Some(question_index) => {
+                let query_str = &rest[question_index + 1..];
 
-            let mut query_map = HashMap::new();
-            for pair in query_str.split('&') {
-                let mut key_value = pair.splitn(2, '=');
-                if let Some(key) = key_value.next() {
-                    let value = key_value.next().unwrap_or("");
-                    query_map.insert(key.to_string(), value.to_string());
+                let mut query_map = HashMap::new();
+                for pair in query_str.split('&') {
+                    let mut key_value = pair.splitn(2, '=');
+                    if let Some(key) = key_value.next() {
+                        let value = key_value.next().unwrap_or("");
+                        query_map.insert(key.to_string(), value.to_string());
+                    }
                 }
+                query = Some(query_map);
+
+                rest[..question_index].to_string()
             }
-            query = Some(query_map);
-        } else {
-            path_only = full_path.clone();
+            None => rest.to_string(),
+        };
+
+        if path_only.is_empty() && scheme.is_some() {
+            path_only = "/".to_string();
         }
 
         HttpPath {
-            full_path: full_path,
+            full_path: path.to_string(),
+            scheme,
+            authority,
             path: path_only,
             query,
             fragment,
         }
     }
+
+    // None when the key is absent, Some(Err(..)) when its value doesn't parse as T
+    pub fn query_as<T: std::str::FromStr>(&self, key: &str) -> Option<Result<T, T::Err>> {
+        self.query
+            .as_ref()
+            .and_then(|query| query.get(key))
+            .map(|value| value.parse::<T>())
+    }
+
+    // The path with repeated slashes collapsed and "." / ".." segments resolved, ".."
+    // never climbs above the root. Used for routing, full_path keeps what was sent.
+    pub fn normalized_path(&self) -> String {
+        normalize_path(&self.path)
+    }
+
+    // asterisk-form, only valid for "OPTIONS * HTTP/1.1"
+    pub fn is_asterisk(&self) -> bool {
+        self.path == "*"
+    }
+
+    pub fn is_absolute(&self) -> bool {
+        self.scheme.is_some()
+    }
+}
+
+fn normalize_path(path: &str) -> String {
+    if path == "*" {
+        return path.to_string();
+    }
+
+    let mut segments: Vec<&str> = Vec::new();
+    let mut trailing_slash = false;
+
+    for segment in path.split('/') {
+        match segment {
+            "" => {}
+            "." => trailing_slash = true,
+            ".." => {
+                segments.pop();
+                trailing_slash = true;
+            }
+            segment => {
+                segments.push(segment);
+                trailing_slash = false;
+            }
+        }
+    }
+    // "/a/b/" ends in an empty segment, "/a/b/.." in a dot segment
+    trailing_slash = trailing_slash || path.ends_with('/');
+
+    let mut normalized = format!("/{}", segments.join("/"));
+    if trailing_slash && !segments.is_empty() {
+        normalized.push('/');
+    }
+    normalized
+}
+
+// scheme = ALPHA *( ALPHA / DIGIT / "+" / "-" / "." ), RFC 3986 section 3.1
+fn is_scheme_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.'
 }
 
 pub struct HttpRequest {
@@ -235,13 +604,314 @@ pub struct HttpRequest {
     pub version: HttpVersion,
     pub headers: HashMap<String, KnownHeader>,
     pub body: Option<String>,
+    // remainder of the path matched by a trailing "/*" route, set by the router
+    pub wildcard: Option<String>,
+    // the request line exactly as the client sent it, empty for requests built in code
+    pub raw_request_line: String,
+    // identifies the request in logs, set by the assign_request_id middleware
+    pub request_id: Option<String>,
+    // parameters of the Content-Type header (charset, boundary, ...) with lowercase names
+    pub content_type_params: HashMap<String, String>,
+    // headers in the order and casing they arrived in, for logging and forwarding verbatim.
+    // Repeated names are all kept here while headers holds the last one.
+    pub raw_headers: Vec<(String, String)>,
+}
+
+impl HttpRequest {
+    // Case-insensitive header lookup
+    pub fn header(&self, name: &str) -> Option<&KnownHeader> {
+        find_header(&self.headers, name)
+    }
+
+    // Collapses runs of whitespace in comma-separated list headers like Accept to a single
+    // space, e.g. "text/html ,\t application/json" becomes "text/html , application/json".
+    // Opaque values such as Authorization and Cookie, quoted strings and raw_headers are
+    // left as they arrived.
+    pub fn normalize_header_whitespace(&mut self) {
+        for (name, header) in self.headers.iter_mut() {
+            if LIST_HEADERS.iter().any(|list_header| list_header.eq_ignore_ascii_case(name)) {
+                *header = KnownHeader::from_str(name, &collapse_whitespace(&header.to_value()));
+            }
+        }
+    }
+
+    pub fn origin(&self) -> Option<&str> {
+        match self.header("Origin") {
+            Some(KnownHeader::Origin(origin)) => Some(origin.as_str()),
+            _ => None,
+        }
+    }
+
+    // Optimistic concurrency for PUT/PATCH: when If-Match names entity tags and none of
+    // them is current_etag (e.g. "\"v2\""), the 412 response to send is returned instead.
+    // "*" matches any current resource, weak tags (W/"..") never match.
+    pub fn check_if_match(&self, current_etag: &str) -> Result<(), HttpResponse> {
+        let tags = match self.header("If-Match") {
+            Some(KnownHeader::IfMatch(tags)) => tags,
+            _ => return Ok(()),
+        };
+
+        let matches = tags.split(',').map(str::trim).any(|tag| tag == "*" || (tag == current_etag && !tag.starts_with("W/")));
+        if matches {
+            return Ok(());
+        }
+
+        let mut headers = HashMap::new();
+        headers.insert("ETag".to_string(), KnownHeader::Other(current_etag.to_string()));
+
+        Err(HttpResponse {
+            version: self.version.clone(),
+            status_code: HttpStatusCode::PreconditionFailed,
+            headers,
+            cookies: Vec::new(),
+            body: None,
+        })
+    }
+
+    // Whether the Accept header allows a response of the given type. Ranges like "text/*"
+    // and "*/*" count, the most specific matching range decides and "q=0" rules a type
+    // out. Without an Accept header any type is fine.
+    pub fn accepts(&self, content_type: &HttpContentType) -> bool {
+        let accept = match self.header("Accept") {
+            Some(KnownHeader::Accept(accept)) if !accept.trim().is_empty() => accept,
+            _ => return true,
+        };
+
+        let media_type = content_type.to_str().split(';').next().unwrap_or("").trim().to_lowercase();
+        let main_type = media_type.split('/').next().unwrap_or("");
+
+        let mut best: Option<(u8, f64)> = None;
+        for range in accept.split(',') {
+            let mut parts = range.split(';');
+            let range = parts.next().unwrap_or("").trim().to_lowercase();
+            let weight = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|weight| weight.trim().parse::<f64>().ok())
+                .unwrap_or(1.0);
+
+            let specificity = if range == media_type {
+                2
+            } else if range.strip_suffix("/*") == Some(main_type) {
+                1
+            } else if range == "*/*" {
+                0
+            } else {
+                continue;
+            };
+
+            if best.is_none_or(|(best_specificity, _)| specificity > best_specificity) {
+                best = Some((specificity, weight));
+            }
+        }
+
+        best.is_some_and(|(_, weight)| weight > 0.0)
+    }
+
+    // Converts the query parameters with FromQuery, a request without a query
+    // string is treated like an empty one
+    pub fn query<T: FromQuery>(&self) -> Result<T, HttpRequestError> {
+        match &self.path.query {
+            Some(query) => T::from_query(query),
+            None => T::from_query(&HashMap::new()),
+        }
+    }
+
+    // Parses an application/json body and converts it with FromJson
+    pub fn json<T: FromJson>(&self) -> Result<T, ParserError> {
+        match self.header("Content-Type") {
+            Some(KnownHeader::ContentType(HttpContentType::ApplicationJson)) => {}
+            Some(KnownHeader::ContentType(other)) => {
+                return Err(ParserError::NotSupported(format!(
+                    "Expected application/json body, found: {}",
+                    other.to_str()
+                )))
+            }
+            _ => {
+                return Err(ParserError::NotSupported(
+                    "Expected application/json body, found no Content-Type".to_string(),
+                ))
+            }
+        }
+
+        match &self.body {
+            Some(body) => Ok(T::from_json(&parse_json(body)?)),
+            None => Err(ParserError::EmptyInput),
+        }
+    }
+
+    // Fields of an HTML form post, the body has to be application/x-www-form-urlencoded
+    pub fn form(&self) -> Result<HashMap<String, Vec<String>>, HttpRequestError> {
+        match self.header("Content-Type") {
+            Some(KnownHeader::ContentType(HttpContentType::ApplicationXWwwFormUrlencoded)) => {}
+            Some(KnownHeader::ContentType(other)) => {
+                return Err(HttpRequestError::InvalidHeader(format!(
+                    "Expected application/x-www-form-urlencoded body, found: {}",
+                    other.to_str()
+                )))
+            }
+            _ => {
+                return Err(HttpRequestError::InvalidHeader(
+                    "Expected application/x-www-form-urlencoded body, found no Content-Type".to_string(),
+                ))
+            }
+        }
+
+        match &self.body {
+            Some(body) => decode_query(body),
+            None => Err(HttpRequestError::InvalidRequest("Missing form body".to_string())),
+        }
+    }
 }
 
 pub struct HttpResponse {
     pub version: HttpVersion,
     pub status_code: HttpStatusCode,
     pub headers: HashMap<String, KnownHeader>,
-    pub body: Option<String>,
+    // kept apart from headers because every cookie needs its own Set-Cookie line
+    pub cookies: Vec<Cookie>,
+    pub body: Option<HttpBody>,
+}
+
+// Response bodies are text for most handlers, raw bytes for things like images
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum HttpBody {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl HttpBody {
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            HttpBody::Text(text) => text.as_bytes(),
+            HttpBody::Bytes(bytes) => bytes,
+        }
+    }
+
+    // Byte bodies are only readable as text when they happen to be valid UTF-8
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            HttpBody::Text(text) => Some(text),
+            HttpBody::Bytes(bytes) => std::str::from_utf8(bytes).ok(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.as_bytes().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl HttpResponse {
+    pub fn json(status_code: HttpStatusCode, value: &JsonType) -> HttpResponse {
+        let body = write_json(value);
+
+        let mut headers = HashMap::new();
+        headers.insert(
+            "Content-Type".to_string(),
+            KnownHeader::ContentType(HttpContentType::ApplicationJson),
+        );
+        headers.insert("Content-Length".to_string(), KnownHeader::ContentLength(body.len()));
+
+        HttpResponse {
+            version: HttpVersion::HTTP11,
+            status_code,
+            headers,
+            cookies: Vec::new(),
+            body: Some(HttpBody::Text(body)),
+        }
+    }
+
+    pub fn text(status_code: HttpStatusCode, body: &str) -> HttpResponse {
+        let mut headers = HashMap::new();
+        headers.insert("Content-Type".to_string(), KnownHeader::ContentType(HttpContentType::TextPlain));
+        headers.insert("Content-Length".to_string(), KnownHeader::ContentLength(body.len()));
+
+        HttpResponse {
+            version: HttpVersion::HTTP11,
+            status_code,
+            headers,
+            cookies: Vec::new(),
+            body: Some(HttpBody::Text(body.to_string())),
+        }
+    }
+
+    pub fn bytes(status_code: HttpStatusCode, content_type: HttpContentType, body: Vec<u8>) -> HttpResponse {
+        let mut headers = HashMap::new();
+        headers.insert("Content-Type".to_string(), KnownHeader::ContentType(content_type));
+        headers.insert("Content-Length".to_string(), KnownHeader::ContentLength(body.len()));
+
+        HttpResponse {
+            version: HttpVersion::HTTP11,
+            status_code,
+            headers,
+            cookies: Vec::new(),
+            body: Some(HttpBody::Bytes(body)),
+        }
+    }
+
+    // Adds a Set-Cookie line, a cookie set earlier with the same name is replaced
+    pub fn set_cookie(&mut self, cookie: Cookie) {
+        self.cookies.retain(|existing| existing.name != cookie.name);
+        self.cookies.push(cookie);
+    }
+
+    // Replaces the body with its gzip encoding when it is at least min_size bytes long,
+    // below that compressing costs more than it saves and can even grow the body
+    pub fn compress(&mut self, min_size: usize) {
+        let body = match &self.body {
+            Some(body) if body.len() >= min_size && !body.is_empty() => body,
+            _ => return,
+        };
+        if !self.status_code.allows_body() || find_header(&self.headers, "Content-Encoding").is_some() {
+            return;
+        }
+
+        let compressed = gzip_compress(body.as_bytes());
+        self.headers.retain(|name, _| !name.eq_ignore_ascii_case("Content-Length"));
+        self.headers.insert("Content-Length".to_string(), KnownHeader::ContentLength(compressed.len()));
+        self.headers.insert("Content-Encoding".to_string(), KnownHeader::ContentEncoding("gzip".to_string()));
+        self.headers.insert("Vary".to_string(), KnownHeader::Other("Accept-Encoding".to_string()));
+        self.body = Some(HttpBody::Bytes(compressed));
+    }
+
+    // Writes the status line and headers as ASCII followed by the raw body bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut head = format!(
+            "{} {} {}\r\n",
+            self.version.to_str(),
+            self.status_code.to_str(),
+            self.status_code.status_text()
+        );
+
+        // without a body the length has to be stated as 0, unless the status rules out a body
+        // altogether or the body follows with chunked encoding
+        let mut headers = self.headers.clone();
+        if !self.status_code.allows_body() {
+            headers.retain(|name, _| !name.eq_ignore_ascii_case("Content-Length"));
+        } else if self.body.is_none()
+            && find_header(&headers, "Content-Length").is_none()
+            && find_header(&headers, "Transfer-Encoding").is_none()
+        {
+            headers.insert("Content-Length".to_string(), KnownHeader::ContentLength(0));
+        }
+
+        write_headers(&headers, &mut head);
+        for cookie in &self.cookies {
+            head.push_str(&format!("Set-Cookie: {}\r\n", cookie));
+        }
+        head.push_str("\r\n");
+
+        let mut output = head.into_bytes();
+        if let Some(body) = &self.body
+            && self.status_code.allows_body()
+        {
+            output.extend_from_slice(body.as_bytes());
+        }
+        output
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
@@ -250,9 +920,25 @@ pub enum HttpRequestError {
     InvalidHeader(String),
     InvalidMethod(String),
     InvalidVersion(String),
+    // length in bytes of a request line above the allowed maximum
+    RequestLineTooLong(usize),
+}
+
+impl std::fmt::Display for HttpRequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            HttpRequestError::InvalidRequest(detail) => write!(f, "Invalid request: {}", detail),
+            HttpRequestError::InvalidHeader(detail) => write!(f, "Invalid header: {}", detail),
+            HttpRequestError::InvalidMethod(method) => write!(f, "Invalid method: {}", method),
+            HttpRequestError::InvalidVersion(version) => write!(f, "Invalid version: {}", version),
+            HttpRequestError::RequestLineTooLong(length) => write!(f, "Request line too long: {} bytes", length),
+        }
+    }
 }
 
 impl HttpMethod {
+    // Method names are case-sensitive (RFC 9110 9.1), "get" is an extension method and
+    // not GET, and extension methods keep the token exactly as it was sent
     fn from_str(method: &str) -> Result<HttpMethod, HttpRequestError> {
         match method {
             "GET" => Ok(HttpMethod::GET),
@@ -264,28 +950,66 @@ impl HttpMethod {
             "PATCH" => Ok(HttpMethod::PATCH),
             "TRACE" => Ok(HttpMethod::TRACE),
             "CONNECT" => Ok(HttpMethod::CONNECT),
-            _ => Err(HttpRequestError::InvalidMethod(method.to_string())),
+            other => {
+                if other.is_empty() || !other.chars().all(is_token_char) {
+                    return Err(HttpRequestError::InvalidMethod(method.to_string()));
+                }
+                Ok(HttpMethod::Other(other.to_string()))
+            }
         }
     }
 
-    fn to_str(self) -> String {
+    pub(crate) fn as_str(&self) -> &str {
         match self {
-            HttpMethod::GET => String::from("GET"),
-            HttpMethod::POST => String::from("POST"),
-            HttpMethod::PUT => String::from("PUT"),
-            HttpMethod::DELETE => String::from("DELETE"),
-            HttpMethod::HEAD => String::from("HEAD"),
-            HttpMethod::OPTIONS => String::from("OPTIONS"),
-            HttpMethod::PATCH => String::from("PATCH"),
-            HttpMethod::TRACE => String::from("TRACE"),
-            HttpMethod::CONNECT => String::from("CONNECT"),
+            HttpMethod::GET => "GET",
+            HttpMethod::POST => "POST",
+            HttpMethod::PUT => "PUT",
+            HttpMethod::DELETE => "DELETE",
+            HttpMethod::HEAD => "HEAD",
+            HttpMethod::OPTIONS => "OPTIONS",
+            HttpMethod::PATCH => "PATCH",
+            HttpMethod::TRACE => "TRACE",
+            HttpMethod::CONNECT => "CONNECT",
+            HttpMethod::Other(method) => method,
         }
     }
 }
 
+// tchar as defined in RFC 7230, section 3.2.6
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
 impl HttpContentType {
+    // Parameters such as "; charset=utf-8" are ignored when matching the media type,
+    // which is compared case-insensitively
     fn from_str(content_type: &str) -> HttpContentType {
-        match content_type {
+        HttpContentType::with_params(content_type).0
+    }
+
+    // Splits a Content-Type value into the media type and its parameters. Parameter
+    // names are lowercased and quoted values are unquoted, malformed parameters are skipped.
+    pub fn with_params(content_type: &str) -> (HttpContentType, HashMap<String, String>) {
+        let mut parts = content_type.split(';');
+        let media_type = parts.next().unwrap_or("").trim().to_lowercase();
+
+        let mut params = HashMap::new();
+        for param in parts {
+            if let Some((name, value)) = param.split_once('=') {
+                let name = name.trim().to_lowercase();
+                let value = value.trim();
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                    .unwrap_or(value);
+
+                if !name.is_empty() {
+                    params.insert(name, value.to_string());
+                }
+            }
+        }
+
+        let content_type = match media_type.as_str() {
             "text/html" => HttpContentType::TextHtml,
             "application/json" => HttpContentType::ApplicationJson,
             "application/xml" => HttpContentType::ApplicationXml,
@@ -294,7 +1018,9 @@ impl HttpContentType {
             "application/x-www-form-urlencoded" => HttpContentType::ApplicationXWwwFormUrlencoded,
             "text/event-stream" => HttpContentType::EventStream,
             other => HttpContentType::Other(other.to_string()),
-        }
+        };
+
+        (content_type, params)
     }
 
     fn to_str(&self) -> &str {
@@ -311,32 +1037,47 @@ impl HttpContentType {
     }
 }
 
-type HttpHandler = fn(HttpRequest) -> HttpResponse;
+pub type HttpHandler = fn(HttpRequest) -> HttpResponse;
+
+// Wraps a handler so it only answers the given method, anything else gets a 405
+pub fn only(method: HttpMethod, handler: HttpHandler) -> impl Fn(HttpRequest) -> HttpResponse + Send + Sync + Clone {
+    move |request: HttpRequest| {
+        if request.method == method {
+            return handler(request);
+        }
+
+        let mut headers = HashMap::new();
+        headers.insert("Allow".to_string(), KnownHeader::Allow(method.as_str().to_string()));
+
+        HttpResponse {
+            version: request.version,
+            status_code: HttpStatusCode::MethodNotAllowed,
+            headers,
+            cookies: Vec::new(),
+            body: None,
+        }
+    }
+}
+
+// Headers are written sorted by name so the serialized output is stable
+fn write_headers(headers: &HashMap<String, KnownHeader>, output: &mut String) {
+    let mut names: Vec<&String> = headers.keys().collect();
+    names.sort();
+
+    for header_name in names {
+        output.push_str(&format!("{}: {}\r\n", header_name, headers[header_name].to_value()));
+    }
+}
 
 pub fn write_http_request(request: HttpRequest) -> Result<String, HttpRequestError> {
     let mut output = format!(
         "{} {} {}\r\n",
-        request.method.to_str(),
+        request.method.as_str(),
         request.path.full_path,
         request.version.to_str()
     );
 
-    for (header_name, header_value) in request.headers.iter() {
-        let header_line = match header_value {
-            KnownHeader::ContentType(ct) => format!("{}: {}\r\n", header_name, ct.to_str()),
-            KnownHeader::ContentLength(len) => format!("{}: {}\r\n", header_name, len),
-            KnownHeader::UserAgent(ua) => format!("{}: {}\r\n", header_name, ua),
-            KnownHeader::Accept(acc) => format!("{}: {}\r\n", header_name, acc),
-            KnownHeader::Host(host) => format!("{}: {}\r\n", header_name, host),
-            KnownHeader::Authorization(auth) => format!("{}: {}\r\n", header_name, auth),
-            KnownHeader::CacheControl(cc) => format!("{}: {}\r\n", header_name, cc),
-            KnownHeader::Connection(conn) => format!("{}: {}\r\n", header_name, conn),
-            KnownHeader::Cookie(cookie) => format!("{}: {}\r\n", header_name, cookie),
-            KnownHeader::Referer(referer) => format!("{}: {}\r\n", header_name, referer),
-            KnownHeader::Other(value) => format!("{}: {}\r\n", header_name, value),
-        };
-        output.push_str(&header_line);
-    }
+    write_headers(&request.headers, &mut output);
 
     output.push_str("\r\n");
     if let Some(body) = request.body {
@@ -347,118 +1088,629 @@ pub fn write_http_request(request: HttpRequest) -> Result<String, HttpRequestErr
 }
 
 pub fn write_http_response(response: HttpResponse) -> Result<String, HttpRequestError> {
-    let mut output = format!(
-        "{} {} {}\r\n",
-        response.version.to_str(),
-        response.status_code.to_str(),
-        response.status_code.status_text()
-    );
-
-    for (header_name, header_value) in response.headers.iter() {
-        let header_line = match header_value {
-            KnownHeader::ContentType(ct) => format!("{}: {}\r\n", header_name, ct.to_str()),
-            KnownHeader::ContentLength(len) => format!("{}: {}\r\n", header_name, len),
-            KnownHeader::UserAgent(ua) => format!("{}: {}\r\n", header_name, ua),
-            KnownHeader::Accept(acc) => format!("{}: {}\r\n", header_name, acc),
-            KnownHeader::Host(host) => format!("{}: {}\r\n", header_name, host),
-            KnownHeader::Authorization(auth) => format!("{}: {}\r\n", header_name, auth),
-            KnownHeader::CacheControl(cc) => format!("{}: {}\r\n", header_name, cc),
-            KnownHeader::Connection(conn) => format!("{}: {}\r\n", header_name, conn),
-            KnownHeader::Cookie(cookie) => format!("{}: {}\r\n", header_name, cookie),
-            KnownHeader::Referer(referer) => format!("{}: {}\r\n", header_name, referer),
-            KnownHeader::Other(value) => format!("{}: {}\r\n", header_name, value),
-        };
-        output.push_str(&header_line);
-    }
+    String::from_utf8(response.to_bytes())
+        .map_err(|_| HttpRequestError::InvalidRequest("Response body is not valid UTF-8".to_string()))
+}
 
-    output.push_str("\r\n");
+// A platform bound to a listener, created by HttpPlatform::bind
+pub struct HttpServer {
+    listener: TcpListener,
+    platform: HttpPlatform,
+}
 
-    if let Some(body) = response.body {
-        output.push_str(body.as_str());
+impl HttpServer {
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
     }
 
-    return Ok(output);
+    pub fn run(self) -> io::Result<()> {
+        self.platform.serve_listener(self.listener)
+    }
 }
 
+// Default limits for the request head, larger heads are answered with 431
+const DEFAULT_MAX_HEADERS: usize = 100;
+const DEFAULT_MAX_HEADER_BYTES: usize = 8 * 1024;
+
+// Bodies shorter than this are sent uncompressed even when the client accepts gzip
+const DEFAULT_COMPRESSION_THRESHOLD: usize = 1024;
+
+// Default upper bound for request bodies accepted by the platform (1 MiB)
+pub(crate) const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+
+pub type HttpLogger = Arc<dyn Fn(&str) + Send + Sync>;
 
 #[derive(Clone)]
 pub struct HttpPlatform {
-    pub app: HttpHandler,
+    pub app: Arc<dyn Fn(HttpRequest) -> HttpResponse + Send + Sync>,
+    pub max_body_size: usize,
+    // number of header lines and size of the whole head (request line included)
+    pub max_headers: usize,
+    pub max_header_bytes: usize,
+    pub max_request_line: usize,
+    // applied to responses that carry a body but no Content-Type
+    pub default_content_type: HttpContentType,
+    // how long a read may stall before an incomplete request is answered with 408
+    pub read_timeout: Option<Duration>,
+    // receives messages about failures that don't reach the client, like handler panics
+    pub logger: Option<HttpLogger>,
+    // minimum body size for gzip responses, compression is off when None
+    pub compression_threshold: Option<usize>,
+    // shared between clones, so every connection thread reports into the same counters
+    counters: Arc<PlatformCounters>,
 }
 
-impl HttpPlatform {
-    pub fn new(app: HttpHandler) -> HttpPlatform {
-        HttpPlatform { app }
-    }
+#[derive(Default)]
+struct PlatformCounters {
+    connections: AtomicUsize,
+    requests: AtomicUsize,
+    max_requests_per_connection: AtomicUsize,
+}
 
-    pub fn handle_request(&self, mut stream: std::net::TcpStream) {
-        let mut buf = [0; 8024];
+// Point-in-time copy of the platform counters
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct HttpPlatformMetrics {
+    pub connections: usize,
+    pub requests: usize,
+    pub max_requests_per_connection: usize,
+}
 
-        loop {
-            match stream.read(&mut buf) {
-                Ok(n) => {
-                    if n == 0 {
-                        break;
-                    }
-                    let buf = String::from_utf8(buf[..n].to_vec()).unwrap();
-                    match read_http_request(buf.as_str()) {
-                        Ok(request) => {
-                            let response = (self.app)(request);
-                            let response_str = write_http_response(response).unwrap();
-                            stream.write(response_str.as_bytes()).unwrap();
-                            stream.flush().unwrap();
-                        }
-                        Err(e) => {
-                            let error_response = HttpResponse {
-                                version: HttpVersion::HTTP11,
-                                status_code: HttpStatusCode::BadRequest,
-                                headers: HashMap::new(),
-                                body: None,
-                            };
-
-                            let response_str = write_http_response(error_response).unwrap();
-                            stream.write(response_str.as_bytes()).unwrap();
-                            stream.flush().unwrap();
-                        }
-                    }
-                }
-                Err(_) => {
-                    return;
-                }
-            }
+impl HttpPlatformMetrics {
+    // Average number of requests served per accepted connection, above 1 once keep-alive kicks in
+    pub fn requests_per_connection(&self) -> f64 {
+        if self.connections == 0 {
+            return 0.0;
         }
+        self.requests as f64 / self.connections as f64
     }
 }
 
-pub fn read_http_response(mut input: &str) -> Result<HttpResponse, HttpRequestError> {
-    let mut state = ParserState::RequestLine;
-    let mut version = HttpVersion::HTTP11;
-    let mut status_code = HttpStatusCode::OK;
-    let mut headers: HashMap<String, KnownHeader> = HashMap::new();
-    let mut body: Option<String> = None;
-    
-    input = input.trim_start();
-    for line in input.lines() {
-        match state {
-            ParserState::RequestLine => {
-                let parts: Vec<&str> = line.split_whitespace().collect();
+impl HttpPlatform {
+    pub fn new<F>(app: F) -> HttpPlatform
+    where
+        F: Fn(HttpRequest) -> HttpResponse + Send + Sync + 'static,
+    {
+        HttpPlatform {
+            app: Arc::new(app),
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            max_headers: DEFAULT_MAX_HEADERS,
+            max_header_bytes: DEFAULT_MAX_HEADER_BYTES,
+            max_request_line: DEFAULT_MAX_REQUEST_LINE,
+            default_content_type: HttpContentType::TextPlain,
+            read_timeout: None,
+            logger: None,
+            compression_threshold: None,
+            counters: Arc::new(PlatformCounters::default()),
+        }
+    }
 
-                if parts.len() < 2 {
-                    return Err(HttpRequestError::InvalidRequest(
-                        "Malformed status line".to_string(),
-                    ));
-                }
+    pub fn metrics(&self) -> HttpPlatformMetrics {
+        HttpPlatformMetrics {
+            connections: self.counters.connections.load(Ordering::Relaxed),
+            requests: self.counters.requests.load(Ordering::Relaxed),
+            max_requests_per_connection: self.counters.max_requests_per_connection.load(Ordering::Relaxed),
+        }
+    }
 
-                version = HttpVersion::from_str(parts[0])?;
-                status_code = HttpStatusCode::from_str(parts[1])?;
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> HttpPlatform {
+        self.max_body_size = max_body_size;
+        self
+    }
 
-                state = ParserState::Headers;
-            }
-            ParserState::Headers => {
-                if line.is_empty() {
-                    state = ParserState::Body;
-                    continue;
-                }
+    pub fn with_max_headers(mut self, max_headers: usize) -> HttpPlatform {
+        self.max_headers = max_headers;
+        self
+    }
+
+    pub fn with_max_header_bytes(mut self, max_header_bytes: usize) -> HttpPlatform {
+        self.max_header_bytes = max_header_bytes;
+        self
+    }
+
+    pub fn with_max_request_line(mut self, max_request_line: usize) -> HttpPlatform {
+        self.max_request_line = max_request_line;
+        self
+    }
+
+    pub fn with_read_timeout(mut self, read_timeout: Duration) -> HttpPlatform {
+        self.read_timeout = Some(read_timeout);
+        self
+    }
+
+    pub fn with_default_content_type(mut self, content_type: HttpContentType) -> HttpPlatform {
+        self.default_content_type = content_type;
+        self
+    }
+
+    // Gzips response bodies of at least 1 KiB for clients that accept it
+    pub fn with_compression(self) -> HttpPlatform {
+        self.with_compression_threshold(DEFAULT_COMPRESSION_THRESHOLD)
+    }
+
+    pub fn with_compression_threshold(mut self, min_size: usize) -> HttpPlatform {
+        self.compression_threshold = Some(min_size);
+        self
+    }
+
+    pub fn with_logger<F>(mut self, logger: F) -> HttpPlatform
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        self.logger = Some(Arc::new(logger));
+        self
+    }
+
+    fn log(&self, message: &str) {
+        if let Some(logger) = &self.logger {
+            logger(message);
+        }
+    }
+
+    // A panicking handler is answered with a 500 so the connection and the server survive it
+    fn run_app(&self, request: HttpRequest) -> HttpResponse {
+        let version = request.version.clone();
+
+        match panic::catch_unwind(AssertUnwindSafe(|| (self.app)(request))) {
+            Ok(response) => response,
+            Err(payload) => {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown panic".to_string());
+                self.log(&format!("Handler panicked: {}", message));
+
+                let mut response = HttpResponse::text(HttpStatusCode::InternalServerError, "Internal Server Error");
+                response.version = version;
+                response
+            }
+        }
+    }
+
+    fn apply_default_content_type(&self, response: &mut HttpResponse) {
+        if response.body.is_some() && find_header(&response.headers, "Content-Type").is_none() {
+            response.headers.insert(
+                "Content-Type".to_string(),
+                KnownHeader::ContentType(self.default_content_type.clone()),
+            );
+        }
+    }
+
+    fn write_error_response(&self, stream: &mut std::net::TcpStream, status_code: HttpStatusCode, message: &str) {
+        let mut headers = HashMap::new();
+        headers.insert("Content-Length".to_string(), KnownHeader::ContentLength(message.len()));
+
+        let mut response = HttpResponse {
+            version: HttpVersion::HTTP11,
+            status_code,
+            headers,
+            cookies: Vec::new(),
+            body: Some(HttpBody::Text(message.to_string())),
+        };
+        self.apply_default_content_type(&mut response);
+
+        let _ = stream.write_all(&response.to_bytes());
+        let _ = stream.flush();
+    }
+
+    // Binds to addr (e.g. "0.0.0.0:8080" or "[::1]:8080") and serves connections until
+    // accepting fails, a bind failure is returned right away
+    pub fn serve<A: ToSocketAddrs>(&self, addr: A) -> io::Result<()> {
+        self.bind(addr)?.run()
+    }
+
+    // Binds without serving yet, so the actual address is known before the first
+    // connection, e.g. the port picked for "127.0.0.1:0"
+    pub fn bind<A: ToSocketAddrs>(&self, addr: A) -> io::Result<HttpServer> {
+        Ok(HttpServer {
+            listener: TcpListener::bind(addr)?,
+            platform: self.clone(),
+        })
+    }
+
+    // Serves connections from an already bound listener, one thread per connection
+    pub fn serve_listener(&self, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let platform = self.clone();
+            std::thread::spawn(move || {
+                platform.handle_request(stream);
+            });
+        }
+        Ok(())
+    }
+
+    pub fn handle_request(&self, mut stream: std::net::TcpStream) {
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut served_on_connection = 0;
+
+        self.counters.connections.fetch_add(1, Ordering::Relaxed);
+
+        if stream.set_read_timeout(self.read_timeout).is_err() {
+            return;
+        }
+
+        loop {
+            let head_end = match read_request_head(&mut stream, &mut buffer, self.max_header_bytes) {
+                Ok(head_end) => head_end,
+                // the rest of the head is never read, so the connection can't be reused
+                Err(BodyReadError::TooLarge) => {
+                    if request_line_length(&buffer) > self.max_request_line {
+                        self.write_error_response(&mut stream, HttpStatusCode::UriTooLong, "Request line too long");
+                    } else {
+                        self.write_error_response(
+                            &mut stream,
+                            HttpStatusCode::RequestHeaderFieldsTooLarge,
+                            "Request head too large",
+                        );
+                    }
+                    drain_before_close(&mut stream);
+                    return;
+                }
+                // a stalled client that already sent part of a request is told so, an idle
+                // keep-alive connection is simply closed
+                Err(BodyReadError::TimedOut) if !buffer.is_empty() => {
+                    self.write_error_response(&mut stream, HttpStatusCode::RequestTimeout, "Request incomplete");
+                    return;
+                }
+                Err(_) => return,
+            };
+
+            let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+            buffer.drain(..head_end);
+
+            if count_header_lines(&head) > self.max_headers {
+                self.write_error_response(&mut stream, HttpStatusCode::RequestHeaderFieldsTooLarge, "Too many headers");
+                drain_before_close(&mut stream);
+                return;
+            }
+
+            let mut request = match read_http_request_with_limit(head.as_str(), self.max_request_line) {
+                Ok(request) => request,
+                Err(HttpRequestError::RequestLineTooLong(_)) => {
+                    self.write_error_response(&mut stream, HttpStatusCode::UriTooLong, "Request line too long");
+                    return;
+                }
+                // the body of a request that can't be parsed has unknown framing, reading on
+                // could serve part of it as the next request
+                Err(e) => {
+                    self.write_error_response(&mut stream, HttpStatusCode::BadRequest, &e.to_string());
+                    drain_before_close(&mut stream);
+                    return;
+                }
+            };
+
+            // the client holds back the body until it is told to go ahead
+            if expects_continue(&request.headers) {
+                match find_header(&request.headers, "Content-Length") {
+                    Some(KnownHeader::ContentLength(length)) if *length > self.max_body_size => {
+                        write_status_response(&mut stream, HttpStatusCode::PayloadTooLarge);
+                        return;
+                    }
+                    _ => write_status_response(&mut stream, HttpStatusCode::Continue),
+                }
+            }
+
+            match read_request_body(&mut stream, &mut buffer, &request.headers, self.max_body_size) {
+                // the body was read in full, so the connection stays usable after a 400
+                Ok(Some(body)) => match String::from_utf8(body) {
+                    Ok(body) => request.body = Some(body),
+                    Err(_) => {
+                        self.write_error_response(&mut stream, HttpStatusCode::BadRequest, "Request body is not valid UTF-8");
+                        continue;
+                    }
+                },
+                Ok(None) => {}
+                Err(BodyReadError::TooLarge) => {
+                    // the rest of the body is never read, so the connection can't be reused
+                    write_status_response(&mut stream, HttpStatusCode::PayloadTooLarge);
+                    return;
+                }
+                Err(BodyReadError::Malformed) => {
+                    write_status_response(&mut stream, HttpStatusCode::BadRequest);
+                    return;
+                }
+                Err(BodyReadError::TimedOut) => {
+                    self.write_error_response(&mut stream, HttpStatusCode::RequestTimeout, "Request body incomplete");
+                    return;
+                }
+                Err(BodyReadError::Closed) => return,
+            }
+
+            let keep_alive = wants_keep_alive(&request);
+            let version = request.version.clone();
+            let gzip_accepted = accepts_gzip(&request.headers);
+
+            let mut response = self.run_app(request);
+            self.apply_default_content_type(&mut response);
+
+            if let Some(min_size) = self.compression_threshold
+                && gzip_accepted
+            {
+                response.compress(min_size);
+            }
+
+            // a handler can still end the connection by answering with "Connection: close"
+            let keep_alive = keep_alive && !has_connection_token(&response.headers, "close");
+            if keep_alive && version == HttpVersion::HTTP10 {
+                response
+                    .headers
+                    .insert("Connection".to_string(), KnownHeader::Connection("keep-alive".to_string()));
+            }
+
+            served_on_connection += 1;
+            self.counters.requests.fetch_add(1, Ordering::Relaxed);
+            self.counters
+                .max_requests_per_connection
+                .fetch_max(served_on_connection, Ordering::Relaxed);
+
+            // a client that went away early only ends its own connection
+            if stream.write_all(&response.to_bytes()).and_then(|_| stream.flush()).is_err() {
+                return;
+            }
+
+            if !keep_alive {
+                return;
+            }
+        }
+    }
+}
+
+pub(crate) enum BodyReadError {
+    TooLarge,
+    Malformed,
+    Closed,
+    TimedOut,
+}
+
+// Reads and drops what the client is still sending before the connection is closed.
+// Closing a socket with unread input makes the kernel reset the connection, and the
+// client may then lose the error response that was just written.
+fn drain_before_close(stream: &mut std::net::TcpStream) {
+    let _ = stream.shutdown(std::net::Shutdown::Write);
+    let _ = stream.set_read_timeout(Some(Duration::from_millis(500)));
+
+    let mut chunk = [0; 8192];
+    let mut drained = 0;
+    while drained < DEFAULT_MAX_BODY_SIZE {
+        match stream.read(&mut chunk) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => drained += n,
+        }
+    }
+}
+
+fn write_status_response(stream: &mut std::net::TcpStream, status_code: HttpStatusCode) {
+    let response = HttpResponse {
+        version: HttpVersion::HTTP11,
+        status_code,
+        headers: HashMap::new(),
+        cookies: Vec::new(),
+        body: None,
+    };
+
+    let _ = stream.write_all(&response.to_bytes());
+    let _ = stream.flush();
+}
+
+// Header names are case-insensitive, but the map is keyed by the name as sent
+pub(crate) fn find_header<'a>(headers: &'a HashMap<String, KnownHeader>, name: &str) -> Option<&'a KnownHeader> {
+    headers
+        .iter()
+        .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value)
+}
+
+// HTTP/1.1 connections stay open unless the client sends "Connection: close",
+// HTTP/1.0 ones are closed unless it asks for "Connection: keep-alive"
+fn wants_keep_alive(request: &HttpRequest) -> bool {
+    if has_connection_token(&request.headers, "close") {
+        return false;
+    }
+    match request.version {
+        HttpVersion::HTTP10 => has_connection_token(&request.headers, "keep-alive"),
+        _ => true,
+    }
+}
+
+// Accept-Encoding lists codings with optional weights, "gzip;q=0" refuses gzip
+fn accepts_gzip(headers: &HashMap<String, KnownHeader>) -> bool {
+    let value = match find_header(headers, "Accept-Encoding") {
+        Some(KnownHeader::Other(value)) => value,
+        _ => return false,
+    };
+
+    value.split(',').any(|coding| {
+        let mut parts = coding.split(';');
+        let name = parts.next().unwrap_or("").trim();
+        let weight = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|weight| weight.trim().parse::<f64>().ok())
+            .unwrap_or(1.0);
+
+        (name.eq_ignore_ascii_case("gzip") || name == "*") && weight > 0.0
+    })
+}
+
+pub(crate) fn has_connection_token(headers: &HashMap<String, KnownHeader>, token: &str) -> bool {
+    match find_header(headers, "Connection") {
+        Some(KnownHeader::Connection(value)) => value.split(',').any(|part| part.trim().eq_ignore_ascii_case(token)),
+        _ => false,
+    }
+}
+
+fn expects_continue(headers: &HashMap<String, KnownHeader>) -> bool {
+    match find_header(headers, "Expect") {
+        Some(KnownHeader::Other(value)) => value.eq_ignore_ascii_case("100-continue"),
+        _ => false,
+    }
+}
+
+pub(crate) fn is_chunked(headers: &HashMap<String, KnownHeader>) -> bool {
+    match find_header(headers, "Transfer-Encoding") {
+        Some(KnownHeader::TransferEncoding(value)) => value.to_lowercase().contains("chunked"),
+        _ => false,
+    }
+}
+
+// Reads more bytes from the stream into the buffer, failing once the peer is gone
+fn fill_buffer<R: Read>(stream: &mut R, buffer: &mut Vec<u8>) -> Result<(), BodyReadError> {
+    let mut chunk = [0; 8024];
+    match stream.read(&mut chunk) {
+        Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {
+            Err(BodyReadError::TimedOut)
+        }
+        Ok(0) | Err(_) => Err(BodyReadError::Closed),
+        Ok(n) => {
+            buffer.extend_from_slice(&chunk[..n]);
+            Ok(())
+        }
+    }
+}
+
+// Returns the length of the request head including the terminating blank line
+// Finds the end of the request head, giving up with TooLarge once more than max_bytes
+// arrived without one
+pub(crate) fn read_request_head<R: Read>(stream: &mut R, buffer: &mut Vec<u8>, max_bytes: usize) -> Result<usize, BodyReadError> {
+    loop {
+        if let Some(pos) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            if pos + 4 > max_bytes {
+                return Err(BodyReadError::TooLarge);
+            }
+            return Ok(pos + 4);
+        }
+        if buffer.len() > max_bytes {
+            return Err(BodyReadError::TooLarge);
+        }
+        fill_buffer(stream, buffer)?;
+    }
+}
+
+// Length of the (possibly still incomplete) request line at the start of buffer
+fn request_line_length(buffer: &[u8]) -> usize {
+    let start = buffer.iter().position(|&byte| byte != b'\r' && byte != b'\n').unwrap_or(buffer.len());
+    let line = &buffer[start..];
+    line.windows(2).position(|window| window == b"\r\n").unwrap_or(line.len())
+}
+
+// Header lines between the request line and the blank line, repeated names count
+// every time
+fn count_header_lines(head: &str) -> usize {
+    head.lines()
+        .skip_while(|line| line.trim().is_empty())
+        .skip(1)
+        .take_while(|line| !line.trim().is_empty())
+        .count()
+}
+
+fn read_line<R: Read>(stream: &mut R, buffer: &mut Vec<u8>) -> Result<String, BodyReadError> {
+    loop {
+        if let Some(pos) = buffer.windows(2).position(|window| window == b"\r\n") {
+            let line = String::from_utf8_lossy(&buffer[..pos]).to_string();
+            buffer.drain(..pos + 2);
+            return Ok(line);
+        }
+        fill_buffer(stream, buffer)?;
+    }
+}
+
+pub(crate) fn read_request_body<R: Read>(
+    stream: &mut R,
+    buffer: &mut Vec<u8>,
+    headers: &HashMap<String, KnownHeader>,
+    max_body_size: usize,
+) -> Result<Option<Vec<u8>>, BodyReadError> {
+    if is_chunked(headers) {
+        return read_chunked_body(stream, buffer, max_body_size).map(Some);
+    }
+
+    let length = match find_header(headers, "Content-Length") {
+        Some(KnownHeader::ContentLength(length)) => *length,
+        Some(_) => return Err(BodyReadError::Malformed),
+        None => return Ok(None),
+    };
+
+    if length > max_body_size {
+        return Err(BodyReadError::TooLarge);
+    }
+
+    if length == 0 {
+        return Ok(None);
+    }
+
+    while buffer.len() < length {
+        fill_buffer(stream, buffer)?;
+    }
+
+    Ok(Some(buffer.drain(..length).collect()))
+}
+
+fn read_chunked_body<R: Read>(
+    stream: &mut R,
+    buffer: &mut Vec<u8>,
+    max_body_size: usize,
+) -> Result<Vec<u8>, BodyReadError> {
+    let mut body = Vec::new();
+
+    loop {
+        let size_line = read_line(stream, buffer)?;
+        // chunk extensions after ';' are ignored
+        let size_str = size_line.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size_str, 16).map_err(|_| BodyReadError::Malformed)?;
+
+        if size == 0 {
+            // skip the trailer section up to the final blank line
+            while !read_line(stream, buffer)?.is_empty() {}
+            return Ok(body);
+        }
+
+        // the size is client controlled, so neither check may overflow
+        if size > max_body_size.saturating_sub(body.len()) {
+            return Err(BodyReadError::TooLarge);
+        }
+
+        let chunk_end = size.checked_add(2).ok_or(BodyReadError::Malformed)?;
+        while buffer.len() < chunk_end {
+            fill_buffer(stream, buffer)?;
+        }
+
+        body.extend(buffer.drain(..size));
+        if buffer.drain(..2).as_slice() != b"\r\n" {
+            return Err(BodyReadError::Malformed);
+        }
+    }
+}
+
+pub fn read_http_response(mut input: &str) -> Result<HttpResponse, HttpRequestError> {
+    let mut state = ParserState::RequestLine;
+    let mut version = HttpVersion::HTTP11;
+    let mut status_code = HttpStatusCode::OK;
+    let mut headers: HashMap<String, KnownHeader> = HashMap::new();
+    let mut cookies = Vec::new();
+    let mut body: Option<String> = None;
+    
+    input = input.trim_start();
+    for line in input.lines() {
+        match state {
+            ParserState::RequestLine => {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+
+                if parts.len() < 2 {
+                    return Err(HttpRequestError::InvalidRequest(
+                        "Malformed status line".to_string(),
+                    ));
+                }
+
+                version = HttpVersion::from_str(parts[0])?;
+                status_code = HttpStatusCode::from_str(parts[1])?;
+
+                state = ParserState::Headers;
+            }
+            ParserState::Headers => {
+                if line.is_empty() {
+                    state = ParserState::Body;
+                    continue;
+                }
 
                 let parts: Vec<&str> = line.splitn(2, ':').collect();
                 if parts.len() != 2 {
@@ -470,6 +1722,13 @@ pub fn read_http_response(mut input: &str) -> Result<HttpResponse, HttpRequestEr
                 let header_name = parts[0].trim();
                 let header_value = parts[1].trim();
 
+                if header_name.eq_ignore_ascii_case("Set-Cookie")
+                    && let Some(cookie) = Cookie::parse(header_value)
+                {
+                    cookies.push(cookie);
+                    continue;
+                }
+
                 headers.insert(
                     header_name.to_string(),
                     KnownHeader::from_str(header_name, header_value),
@@ -490,7 +1749,8 @@ pub fn read_http_response(mut input: &str) -> Result<HttpResponse, HttpRequestEr
         version,
         status_code,
         headers,
-        body,
+        cookies,
+        body: body.map(HttpBody::Text),
     })
 }
 
@@ -500,18 +1760,34 @@ enum ParserState {
     Body,
 }
 
-pub fn read_http_request(mut input: &str) -> Result<HttpRequest, HttpRequestError> {
+// Default upper bound for the request line, most of which is usually the query string
+const DEFAULT_MAX_REQUEST_LINE: usize = 8 * 1024;
+
+pub fn read_http_request(input: &str) -> Result<HttpRequest, HttpRequestError> {
+    read_http_request_with_limit(input, DEFAULT_MAX_REQUEST_LINE)
+}
+
+// Like read_http_request, but a request line longer than max_request_line bytes is
+// rejected with RequestLineTooLong before any of it is parsed
+pub fn read_http_request_with_limit(mut input: &str, max_request_line: usize) -> Result<HttpRequest, HttpRequestError> {
     let mut state = ParserState::RequestLine;
     let mut method = HttpMethod::GET;
     let mut path = HttpPath::from_str("/");
     let mut version = HttpVersion::HTTP11;
     let mut headers: HashMap<String, KnownHeader> = HashMap::new();
     let mut body: Option<String> = None;
+    let mut raw_request_line = String::new();
+    let mut content_type_params: HashMap<String, String> = HashMap::new();
+    let mut raw_headers: Vec<(String, String)> = Vec::new();
 
     input = input.trim_start();
     for line in input.lines() {
         match state {
             ParserState::RequestLine => {
+                if line.len() > max_request_line {
+                    return Err(HttpRequestError::RequestLineTooLong(line.len()));
+                }
+                raw_request_line = line.to_string();
                 let parts: Vec<&str> = line.split_whitespace().collect();
 
                 if parts.len() != 3 {
@@ -537,6 +1813,15 @@ pub fn read_http_request(mut input: &str) -> Result<HttpRequest, HttpRequestErro
                     continue;
                 }
 
+                // a header line starting with whitespace is obsolete line folding
+                // (RFC 7230 3.2.4), it is rejected rather than guessed at
+                if line.starts_with([' ', '\t']) {
+                    return Err(HttpRequestError::InvalidHeader(
+                        format!("Folded header line: {}", line.trim()),
+                    ));
+                }
+
+                // an empty value ("X-Empty:") is kept as an empty string
                 let parts: Vec<&str> = line.splitn(2, ':').collect();
                 if parts.len() != 2 {
                     return Err(HttpRequestError::InvalidHeader(
@@ -547,6 +1832,11 @@ pub fn read_http_request(mut input: &str) -> Result<HttpRequest, HttpRequestErro
                 let header_name = parts[0].trim();
                 let header_value = parts[1].trim();
 
+                if header_name.eq_ignore_ascii_case("Content-Type") {
+                    content_type_params = HttpContentType::with_params(header_value).1;
+                }
+
+                raw_headers.push((header_name.to_string(), header_value.to_string()));
                 headers.insert(
                     header_name.to_string(),
                     KnownHeader::from_str(header_name, header_value),
@@ -564,18 +1854,150 @@ pub fn read_http_request(mut input: &str) -> Result<HttpRequest, HttpRequestErro
         }
     }
 
+    // nothing but whitespace, e.g. a client that connected and closed again
+    if matches!(state, ParserState::RequestLine) {
+        return Err(HttpRequestError::InvalidRequest("Missing request line".to_string()));
+    }
+
+    // "Content-Length: 0" announces an empty body, which handlers can tell apart from a
+    // request that has none
+    if body.is_none() && matches!(find_header(&headers, "Content-Length"), Some(KnownHeader::ContentLength(0))) {
+        body = Some(String::new());
+    }
+
     Ok(HttpRequest {
         method: method,
         path: path,
         version: version,
         headers: headers,
         body: body,
+        wildcard: None,
+        request_id: None,
+        content_type_params,
+        raw_headers,
+        raw_request_line,
     })
 }
 
+// Parses one request off the front of input and returns whatever follows it, so
+// pipelined requests can be read one after another. The body is taken from
+// Content-Length, a request without one has no body.
+pub fn read_http_request_partial(input: &str) -> Result<(HttpRequest, &str), HttpRequestError> {
+    // empty lines before a request line are ignored (RFC 7230 3.5)
+    let input = input.trim_start_matches(['\r', '\n']);
+
+    let head_end = match input.find("\r\n\r\n") {
+        Some(pos) => pos + 4,
+        None => return Err(HttpRequestError::InvalidRequest("Incomplete request head".to_string())),
+    };
+
+    let mut request = read_http_request(&input[..head_end])?;
+    let rest = &input[head_end..];
+
+    if is_chunked(&request.headers) {
+        return Err(HttpRequestError::InvalidRequest(
+            "Chunked bodies are not supported when parsing from a string".to_string(),
+        ));
+    }
+
+    let length = match find_header(&request.headers, "Content-Length") {
+        Some(KnownHeader::ContentLength(length)) => *length,
+        Some(_) => return Err(HttpRequestError::InvalidHeader("Invalid Content-Length".to_string())),
+        None => 0,
+    };
+
+    if rest.len() < length || !rest.is_char_boundary(length) {
+        return Err(HttpRequestError::InvalidRequest("Incomplete request body".to_string()));
+    }
+
+    if length > 0 {
+        request.body = Some(rest[..length].to_string());
+    }
+
+    Ok((request, &rest[length..]))
+}
+
+// Reads the head line by line up to the blank line, then exactly Content-Length bytes
+// of body, so the request never has to be collected into one string first
+pub fn read_http_request_buf<R: BufRead>(reader: &mut R) -> Result<HttpRequest, HttpRequestError> {
+    let (mut request, mut body_reader) = read_http_request_streaming(reader)?;
+
+    let length = body_reader.limit() as usize;
+    if length > 0 {
+        let mut body = Vec::with_capacity(length);
+        match body_reader.read_to_end(&mut body) {
+            Ok(read) if read == length => {}
+            _ => return Err(HttpRequestError::InvalidRequest("Incomplete request body".to_string())),
+        }
+
+        let body = String::from_utf8(body)
+            .map_err(|_| HttpRequestError::InvalidRequest("Request body is not valid UTF-8".to_string()))?;
+        request.body = Some(body);
+    }
+
+    Ok(request)
+}
+
+// Reads only the head and hands back the reader limited to the Content-Length bytes of
+// the body, so large uploads can be streamed elsewhere instead of held in memory. Once the
+// body reader is exhausted the underlying reader is positioned at the next request.
+// Chunked bodies are rejected, their framing can't be expressed as a byte count.
+pub fn read_http_request_streaming<R: BufRead>(
+    reader: &mut R,
+) -> Result<(HttpRequest, io::Take<&mut R>), HttpRequestError> {
+    let mut head = String::new();
+    let mut consumed = 0;
+
+    loop {
+        // reads at most one byte past the limit, so a line without an end can't grow forever
+        let mut line = String::new();
+        let remaining = DEFAULT_MAX_HEADER_BYTES.saturating_sub(consumed) as u64 + 1;
+        let read = (&mut *reader)
+            .take(remaining)
+            .read_line(&mut line)
+            .map_err(|e| HttpRequestError::InvalidRequest(format!("Failed to read request: {}", e)))?;
+
+        consumed += read;
+        if consumed > DEFAULT_MAX_HEADER_BYTES {
+            return Err(HttpRequestError::InvalidRequest("Request head too large".to_string()));
+        }
+        if read == 0 {
+            return Err(HttpRequestError::InvalidRequest("Incomplete request head".to_string()));
+        }
+
+        // empty lines before the request line are ignored (RFC 7230 3.5)
+        if head.is_empty() && line.trim().is_empty() {
+            continue;
+        }
+
+        let end_of_head = line == "\r\n" || line == "\n";
+        head.push_str(&line);
+        if end_of_head {
+            break;
+        }
+    }
+
+    let request = read_http_request(&head)?;
+
+    if find_header(&request.headers, "Transfer-Encoding").is_some() {
+        return Err(HttpRequestError::InvalidHeader(
+            "Transfer-Encoding is not supported when streaming a request body".to_string(),
+        ));
+    }
+
+    let length = match find_header(&request.headers, "Content-Length") {
+        Some(KnownHeader::ContentLength(length)) => *length,
+        Some(_) => return Err(HttpRequestError::InvalidHeader("Invalid Content-Length".to_string())),
+        None => 0,
+    };
+
+    Ok((request, Read::take(reader, length as u64)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::start_test_platform;
 
     #[test]
     fn read_http_get_request() {
@@ -700,11 +2122,12 @@ mod tests {
 
     #[test]
     fn read_http_post_request_with_body_spaces() {
+        // header lines can't be indented, that would be line folding
         let request_str = r#"
-        POST /submit HTTP/1.1
-        Host: example.com
-        Content-Type: application/json
-        Content-Length: 36
+POST /submit HTTP/1.1
+Host: example.com
+Content-Type: application/json
+Content-Length: 36
 
         {
             "key1": "value1",
@@ -749,7 +2172,8 @@ mod tests {
                 headers.insert("Content-Length".to_string(), KnownHeader::ContentLength(13));
                 headers
             },
-            body: Some("<h1>Hello</h1>".to_string()),
+            cookies: Vec::new(),
+            body: Some(HttpBody::Text("<h1>Hello</h1>".to_string())),
         };
 
         let response_str = write_http_response(response).unwrap();
@@ -757,4 +2181,1462 @@ mod tests {
 
         assert_eq!(response_str, expected_response_str);
     }
+
+    #[test]
+    fn write_response_with_two_cookies() {
+        let mut response = HttpResponse::text(HttpStatusCode::OK, "ok");
+        response.set_cookie(Cookie::new("session", "abc"));
+        response.set_cookie(Cookie {
+            path: Some("/".to_string()),
+            http_only: true,
+            ..Cookie::new("theme", "dark")
+        });
+        response.set_cookie(Cookie::new("session", "def"));
+
+        let written = write_http_response(response).unwrap();
+        let set_cookies: Vec<&str> = written.lines().filter(|line| line.starts_with("Set-Cookie:")).collect();
+        assert_eq!(set_cookies, vec!["Set-Cookie: theme=dark; Path=/; HttpOnly", "Set-Cookie: session=def"]);
+
+        let read = read_http_response(&written).unwrap();
+        assert_eq!(read.cookies.len(), 2);
+        assert_eq!(read.cookies[0].name, "theme");
+        assert!(read.cookies[0].http_only);
+        assert_eq!(read.cookies[1], Cookie::new("session", "def"));
+    }
+
+    #[test]
+    fn write_http_response_binary_body() {
+        let mut headers = HashMap::new();
+        headers.insert("Content-Length".to_string(), KnownHeader::ContentLength(4));
+
+        let response = HttpResponse {
+            version: HttpVersion::HTTP11,
+            status_code: HttpStatusCode::OK,
+            headers,
+            cookies: Vec::new(),
+            body: Some(HttpBody::Bytes(vec![0x89, b'P', 0xff, 0x00])),
+        };
+
+        let mut expected = b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\n".to_vec();
+        expected.extend_from_slice(&[0x89, b'P', 0xff, 0x00]);
+        assert_eq!(response.to_bytes(), expected);
+
+        assert!(matches!(write_http_response(response), Err(HttpRequestError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn response_text_and_bytes_constructors() {
+        let response = HttpResponse::text(HttpStatusCode::OK, "hello");
+        assert_eq!(response.body, Some(HttpBody::Text("hello".to_string())));
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some(&KnownHeader::ContentType(HttpContentType::TextPlain))
+        );
+        assert_eq!(response.headers.get("Content-Length"), Some(&KnownHeader::ContentLength(5)));
+
+        let png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+        let response = HttpResponse::bytes(HttpStatusCode::OK, HttpContentType::Other("image/png".to_string()), png.clone());
+        assert_eq!(response.body.as_ref().map(|body| body.as_bytes()), Some(png.as_slice()));
+        assert_eq!(response.body.as_ref().and_then(|body| body.as_text()), None);
+        assert_eq!(response.headers.get("Content-Length"), Some(&KnownHeader::ContentLength(8)));
+        assert!(response.to_bytes().ends_with(&png));
+    }
+
+    #[test]
+    fn read_http_method_is_case_sensitive() {
+        let request_str = "get / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let request = read_http_request(request_str).unwrap();
+
+        assert_eq!(request.method, HttpMethod::Other("get".to_string()));
+        assert_eq!(HttpMethod::from_str("Post"), Ok(HttpMethod::Other("Post".to_string())));
+        assert_eq!(HttpMethod::from_str("GET"), Ok(HttpMethod::GET));
+    }
+
+    #[test]
+    fn read_http_method_custom() {
+        let request_str = "PROPFIND /files HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let request = read_http_request(request_str).unwrap();
+
+        assert_eq!(request.method, HttpMethod::Other("PROPFIND".to_string()));
+        assert_eq!(request.method.as_str(), "PROPFIND");
+
+        let request = read_http_request("propfind /files HTTP/1.1\r\nHost: example.com\r\n\r\n").unwrap();
+        assert_eq!(request.method, HttpMethod::Other("propfind".to_string()));
+    }
+
+    #[test]
+    fn read_http_request_keeps_raw_request_line() {
+        let request = read_http_request("get /a//b?x=1 HTTP/1.1\r\nHost: example.com\r\n\r\n").unwrap();
+
+        assert_eq!(request.method, HttpMethod::Other("get".to_string()));
+        assert_eq!(request.raw_request_line, "get /a//b?x=1 HTTP/1.1");
+
+        let result = read_http_request("bR(W /a HTTP/1.1\r\nHost: example.com\r\n\r\n");
+        assert!(matches!(result, Err(HttpRequestError::InvalidMethod(method)) if method == "bR(W"));
+    }
+
+    #[test]
+    fn read_http_request_with_empty_header_value() {
+        let request = read_http_request("GET / HTTP/1.1\r\nHost: example.com\r\nX-Empty:\r\nX-Blank:   \r\n\r\n").unwrap();
+
+        assert_eq!(request.header("X-Empty"), Some(&KnownHeader::Other("".to_string())));
+        assert_eq!(request.header("X-Blank"), Some(&KnownHeader::Other("".to_string())));
+    }
+
+    #[test]
+    fn read_http_request_with_folded_header() {
+        let request_str = "GET / HTTP/1.1\r\nHost: example.com\r\nX-Long: first\r\n second\r\n\r\n";
+
+        assert!(matches!(read_http_request(request_str), Err(HttpRequestError::InvalidHeader(_))));
+
+        let request_str = "GET / HTTP/1.1\r\nHost: example.com\r\nX-Long: first\r\n\tsecond\r\n\r\n";
+        assert!(matches!(read_http_request(request_str), Err(HttpRequestError::InvalidHeader(_))));
+
+        // whitespace before the first header or before every header is folding as well
+        for request_str in [
+            "GET / HTTP/1.1\r\n Host: x\r\n\r\n",
+            "GET / HTTP/1.1\r\n\tHost: x\r\n\r\n",
+            "GET / HTTP/1.1\r\n  Host: x\r\n  Accept: */*\r\n\r\n",
+            "GET / HTTP/1.1\r\n  Host: x\r\nAccept: */*\r\n\r\n",
+        ] {
+            assert!(matches!(read_http_request(request_str), Err(HttpRequestError::InvalidHeader(_))), "{:?}", request_str);
+        }
+    }
+
+    #[test]
+    fn read_http_method_invalid_token() {
+        assert_eq!(
+            HttpMethod::from_str("PROP FIND"),
+            Err(HttpRequestError::InvalidMethod("PROP FIND".to_string()))
+        );
+    }
+
+    #[test]
+    fn read_http_request_absolute_form_target() {
+        let request_str = "GET http://example.com:8080/path?x=1#top HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let request = read_http_request(request_str).unwrap();
+
+        assert_eq!(request.path.full_path, "http://example.com:8080/path?x=1#top");
+        assert_eq!(request.path.scheme, Some("http".to_string()));
+        assert_eq!(request.path.authority, Some("example.com:8080".to_string()));
+        assert_eq!(request.path.path, "/path");
+        assert_eq!(request.path.query.unwrap().get("x"), Some(&"1".to_string()));
+        assert_eq!(request.path.fragment, Some("top".to_string()));
+
+        let path = HttpPath::from_str("https://example.com");
+        assert_eq!(path.authority, Some("example.com".to_string()));
+        assert_eq!(path.path, "/");
+    }
+
+    #[test]
+    fn read_http_request_origin_form_target() {
+        let request_str = "GET /search?q=rust HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let request = read_http_request(request_str).unwrap();
+
+        assert!(!request.path.is_absolute());
+        assert_eq!(request.path.scheme, None);
+        assert_eq!(request.path.authority, None);
+        assert_eq!(request.path.path, "/search");
+        assert_eq!(request.path.query.unwrap().get("q"), Some(&"rust".to_string()));
+    }
+
+    #[test]
+    fn read_http_request_asterisk_form_target() {
+        let request_str = "OPTIONS * HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let request = read_http_request(request_str).unwrap();
+
+        assert_eq!(request.method, HttpMethod::OPTIONS);
+        assert!(request.path.is_asterisk());
+        assert_eq!(request.path.scheme, None);
+        assert_eq!(request.path.query, None);
+    }
+
+    fn send_raw(addr: std::net::SocketAddr, request: &[u8]) -> String {
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        stream.write_all(request).unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn echo_body(req: HttpRequest) -> HttpResponse {
+        let body = req.body.unwrap_or_default();
+        let mut headers = HashMap::new();
+        headers.insert("Content-Length".to_string(), KnownHeader::ContentLength(body.len()));
+
+        HttpResponse {
+            version: req.version,
+            status_code: HttpStatusCode::OK,
+            headers,
+            cookies: Vec::new(),
+            body: Some(HttpBody::Text(body)),
+        }
+    }
+
+    #[test]
+    fn read_http_request_from_buf_reader() {
+        let input: &[u8] = b"POST /people HTTP/1.1\r\nHost: example.com\r\nContent-Length: 5\r\n\r\nhelloGET /next HTTP/1.1\r\n\r\n";
+        let mut reader = std::io::BufReader::new(input);
+
+        let request = read_http_request_buf(&mut reader).unwrap();
+        assert_eq!(request.method, HttpMethod::POST);
+        assert_eq!(request.path.path, "/people");
+        assert_eq!(request.headers.get("Host"), Some(&KnownHeader::Host("example.com".to_string())));
+        assert_eq!(request.body, Some("hello".to_string()));
+
+        let request = read_http_request_buf(&mut reader).unwrap();
+        assert_eq!(request.path.path, "/next");
+        assert_eq!(request.body, None);
+
+        assert!(matches!(read_http_request_buf(&mut reader), Err(HttpRequestError::InvalidRequest(_))));
+
+        let mut reader = std::io::BufReader::new(&b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\nshort"[..]);
+        assert!(matches!(read_http_request_buf(&mut reader), Err(HttpRequestError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn read_pipelined_requests() {
+        let input = "GET /first HTTP/1.1\r\nHost: example.com\r\n\r\n\
+            POST /second HTTP/1.1\r\nHost: example.com\r\nContent-Length: 5\r\n\r\nhello\
+            GET /third HTTP/1.1\r\nHost: example.com\r\n\r\n";
+
+        let (first, rest) = read_http_request_partial(input).unwrap();
+        assert_eq!(first.path.path, "/first");
+        assert_eq!(first.body, None);
+
+        let (second, rest) = read_http_request_partial(rest).unwrap();
+        assert_eq!(second.method, HttpMethod::POST);
+        assert_eq!(second.path.path, "/second");
+        assert_eq!(second.body, Some("hello".to_string()));
+
+        let (third, rest) = read_http_request_partial(rest).unwrap();
+        assert_eq!(third.path.path, "/third");
+        assert_eq!(rest, "");
+
+        assert!(matches!(
+            read_http_request_partial("GET / HTTP/1.1\r\nHost: example.com\r\n"),
+            Err(HttpRequestError::InvalidRequest(_))
+        ));
+        assert!(matches!(
+            read_http_request_partial("POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\nshort"),
+            Err(HttpRequestError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn handle_request_pipelined_requests() {
+        let server = start_test_platform(HttpPlatform::new(echo_body));
+        let addr = server.addr();
+
+        let response = send_raw(
+            addr,
+            b"POST /a HTTP/1.1\r\nHost: example.com\r\nContent-Length: 3\r\n\r\none\
+              POST /b HTTP/1.1\r\nHost: example.com\r\nContent-Length: 3\r\n\r\ntwo",
+        );
+
+        let first_end = response.find("one").unwrap();
+        let second_end = response.find("two").unwrap();
+        assert!(first_end < second_end);
+        assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 2);
+    }
+
+    #[test]
+    fn handle_request_body_within_limit() {
+        let server = start_test_platform(HttpPlatform::new(echo_body).with_max_body_size(16));
+        let addr = server.addr();
+
+        let response = send_raw(addr, b"POST /upload HTTP/1.1\r\nHost: example.com\r\nContent-Length: 5\r\n\r\nhello");
+        let response = read_http_response(response.as_str()).unwrap();
+
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+        assert_eq!(response.body, Some(HttpBody::Text("hello".to_string())));
+    }
+
+    #[test]
+    fn handle_request_content_length_exceeding_limit() {
+        let server = start_test_platform(HttpPlatform::new(echo_body).with_max_body_size(16));
+        let addr = server.addr();
+
+        let mut request = b"POST /upload HTTP/1.1\r\nHost: example.com\r\nContent-Length: 100\r\n\r\n".to_vec();
+        request.extend_from_slice(&[b'a'; 100]);
+        let response = send_raw(addr, &request);
+        let response = read_http_response(response.as_str()).unwrap();
+
+        assert_eq!(response.status_code, HttpStatusCode::PayloadTooLarge);
+    }
+
+    #[test]
+    fn handle_request_chunked_body_exceeding_limit() {
+        let server = start_test_platform(HttpPlatform::new(echo_body).with_max_body_size(16));
+        let addr = server.addr();
+
+        let request = b"POST /upload HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: chunked\r\n\r\n\
+            a\r\n0123456789\r\na\r\n0123456789\r\n0\r\n\r\n";
+        let response = send_raw(addr, request);
+        let response = read_http_response(response.as_str()).unwrap();
+
+        assert_eq!(response.status_code, HttpStatusCode::PayloadTooLarge);
+    }
+
+    #[test]
+    fn handle_request_huge_chunk_size() {
+        let chunked = |chunks: &str| {
+            format!("POST /upload HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: chunked\r\n\r\n{}", chunks)
+        };
+
+        let server = start_test_platform(HttpPlatform::new(echo_body).with_max_body_size(16));
+        for chunks in ["ffffffffffffffff\r\n", "1\r\na\r\nffffffffffffffff\r\n", "fffffffffffffffe\r\n"] {
+            let response = send_raw(server.addr(), chunked(chunks).as_bytes());
+            let response = read_http_response(response.as_str()).unwrap();
+            assert_eq!(response.status_code, HttpStatusCode::PayloadTooLarge, "{:?}", chunks);
+        }
+
+        // without a limit the size still can't wrap around when the line ending is added
+        let server = start_test_platform(HttpPlatform::new(echo_body).with_max_body_size(usize::MAX));
+        let response = send_raw(server.addr(), chunked("ffffffffffffffff\r\n").as_bytes());
+        let response = read_http_response(response.as_str()).unwrap();
+        assert_eq!(response.status_code, HttpStatusCode::BadRequest);
+
+        let response = send_raw(server.addr(), b"POST /echo HTTP/1.1\r\nHost: example.com\r\nContent-Length: 2\r\n\r\nok");
+        let response = read_http_response(response.as_str()).unwrap();
+        assert_eq!(response.body, Some(HttpBody::Text("ok".to_string())));
+    }
+
+    #[test]
+    fn handle_request_chunked_body_within_limit() {
+        let server = start_test_platform(HttpPlatform::new(echo_body).with_max_body_size(16));
+        let addr = server.addr();
+
+        let request = b"POST /upload HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: chunked\r\n\r\n\
+            5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n";
+        let response = send_raw(addr, request);
+        let response = read_http_response(response.as_str()).unwrap();
+
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+        assert_eq!(response.body, Some(HttpBody::Text("hello world".to_string())));
+    }
+
+    fn panicking_handler(req: HttpRequest) -> HttpResponse {
+        if req.path.normalized_path() == "/panic" {
+            panic!("handler failed for {}", req.path.normalized_path());
+        }
+        echo_body(req)
+    }
+
+    #[test]
+    fn handle_request_handler_panic() {
+        let logged = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = logged.clone();
+        let platform = HttpPlatform::new(panicking_handler)
+            .with_logger(move |message| log.lock().unwrap().push(message.to_string()));
+        let server = start_test_platform(platform);
+        let addr = server.addr();
+
+        let request = b"GET /panic HTTP/1.1\r\nHost: example.com\r\n\r\n\
+            POST /echo HTTP/1.1\r\nHost: example.com\r\nContent-Length: 5\r\n\r\nhello";
+        let response = send_raw(addr, request);
+
+        let (first, second) = response.split_at(response.find("HTTP/1.1 200").unwrap());
+        let first = read_http_response(first).unwrap();
+        assert_eq!(first.status_code, HttpStatusCode::InternalServerError);
+        assert_eq!(first.body, Some(HttpBody::Text("Internal Server Error".to_string())));
+
+        // the connection is still served after the panic
+        let second = read_http_response(second).unwrap();
+        assert_eq!(second.body, Some(HttpBody::Text("hello".to_string())));
+
+        assert_eq!(*logged.lock().unwrap(), vec!["Handler panicked: handler failed for /panic".to_string()]);
+    }
+
+    #[test]
+    fn serve_bind_failure() {
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = taken.local_addr().unwrap();
+
+        let error = HttpPlatform::new(echo_body).serve(addr).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AddrInUse);
+    }
+
+    #[test]
+    fn serve_listener_on_ephemeral_port() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert_ne!(addr.port(), 0);
+
+        std::thread::spawn(move || HttpPlatform::new(echo_body).serve_listener(listener));
+
+        let response = send_raw(addr, b"POST /echo HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2\r\n\r\nhi");
+        let response = read_http_response(response.as_str()).unwrap();
+        assert_eq!(response.body, Some(HttpBody::Text("hi".to_string())));
+    }
+
+    #[test]
+    fn bind_reports_ephemeral_port() {
+        let server = HttpPlatform::new(echo_body).bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        assert!(addr.ip().is_loopback());
+        assert_ne!(addr.port(), 0);
+
+        std::thread::spawn(move || server.run());
+
+        let response = send_raw(addr, b"POST /echo HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello");
+        let response = read_http_response(response.as_str()).unwrap();
+        assert_eq!(response.body, Some(HttpBody::Text("hello".to_string())));
+    }
+
+    #[test]
+    fn read_http_request_without_headers() {
+        let request = read_http_request("GET /health HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(request.method, HttpMethod::GET);
+        assert_eq!(request.path.normalized_path(), "/health");
+        assert!(request.headers.is_empty());
+        assert_eq!(request.body, None);
+
+        // the same holds for pipelined health checks
+        let input = "GET /health HTTP/1.1\r\n\r\nGET /health HTTP/1.0\r\n\r\n";
+        let (first, rest) = read_http_request_partial(input).unwrap();
+        assert!(first.headers.is_empty());
+        assert_eq!(first.body, None);
+
+        let (second, rest) = read_http_request_partial(rest).unwrap();
+        assert_eq!(second.version, HttpVersion::HTTP10);
+        assert!(second.headers.is_empty());
+        assert_eq!(second.body, None);
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn handle_request_without_headers() {
+        let server = start_test_platform(HttpPlatform::new(echo_body));
+        let addr = server.addr();
+
+        let response = send_raw(addr, b"GET /health HTTP/1.1\r\n\r\n");
+        let response = read_http_response(response.as_str()).unwrap();
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+    }
+
+    #[test]
+    fn read_http_request_streaming_large_body() {
+        let length = 4 * 1024 * 1024 + 7;
+        let mut input = format!("PUT /upload HTTP/1.1\r\nHost: example.com\r\nContent-Length: {}\r\n\r\n", length).into_bytes();
+        input.extend((0..length).map(|i| (i % 251) as u8));
+        input.extend_from_slice(b"GET /next HTTP/1.1\r\nHost: example.com\r\n\r\n");
+
+        let mut reader = io::BufReader::new(input.as_slice());
+        let (request, mut body) = read_http_request_streaming(&mut reader).unwrap();
+        assert_eq!(request.path.normalized_path(), "/upload");
+        assert_eq!(request.body, None);
+
+        let mut sink: Vec<u8> = Vec::new();
+        let copied = io::copy(&mut body, &mut sink).unwrap();
+        assert_eq!(copied, length as u64);
+        assert_eq!(sink.len(), length);
+        assert_eq!(sink[length - 1], ((length - 1) % 251) as u8);
+
+        let next = read_http_request_buf(&mut reader).unwrap();
+        assert_eq!(next.path.normalized_path(), "/next");
+    }
+
+    #[test]
+    fn read_http_request_streaming_rejects_chunked_body() {
+        let input: &[u8] = b"PUT /upload HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n";
+        let mut reader = io::BufReader::new(input);
+
+        match read_http_request_streaming(&mut reader) {
+            Err(HttpRequestError::InvalidHeader(detail)) => assert!(detail.contains("Transfer-Encoding"), "{}", detail),
+            _ => panic!("a chunked body must not be read as an empty one"),
+        }
+        assert!(read_http_request_buf(&mut io::BufReader::new(input)).is_err());
+    }
+
+    #[test]
+    fn read_http_request_streaming_limits_head_size() {
+        // one endless header line and a head of many short lines are both cut off
+        let mut long_line = b"GET / HTTP/1.1\r\nX-Long: ".to_vec();
+        long_line.extend(std::iter::repeat_n(b'a', 1024 * 1024));
+        let mut many_lines = b"GET / HTTP/1.1\r\n".to_vec();
+        for i in 0..2000 {
+            many_lines.extend_from_slice(format!("X-{}: a\r\n", i).as_bytes());
+        }
+        let blank_lines = b"\r\n".repeat(1024 * 1024);
+
+        for input in [long_line, many_lines, blank_lines] {
+            let mut reader = io::BufReader::new(input.as_slice());
+            assert_eq!(
+                read_http_request_streaming(&mut reader).err(),
+                Some(HttpRequestError::InvalidRequest("Request head too large".to_string()))
+            );
+        }
+    }
+
+    // Sends one request without closing the write side and reads until the body arrived
+    fn send_and_read(stream: &mut std::net::TcpStream, request: &[u8], body: &str) -> String {
+        stream.write_all(request).unwrap();
+
+        let mut response = Vec::new();
+        let mut chunk = [0; 1024];
+        while !String::from_utf8_lossy(&response).ends_with(body) {
+            let read = stream.read(&mut chunk).unwrap();
+            assert!(read > 0, "connection closed early");
+            response.extend_from_slice(&chunk[..read]);
+        }
+        String::from_utf8(response).unwrap()
+    }
+
+    #[test]
+    fn http10_closes_by_default() {
+        let server = start_test_platform(HttpPlatform::new(echo_body));
+        let addr = server.addr();
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+
+        // the server closes after the response, so reading to the end doesn't wait for a timeout
+        stream.write_all(b"POST /echo HTTP/1.0\r\nContent-Length: 5\r\n\r\nhello").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let response = read_http_response(response.as_str()).unwrap();
+        assert_eq!(response.body, Some(HttpBody::Text("hello".to_string())));
+        assert_eq!(response.headers.get("Connection"), None);
+
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let response = send_and_read(
+            &mut stream,
+            b"POST /echo HTTP/1.0\r\nConnection: keep-alive\r\nContent-Length: 5\r\n\r\nhello",
+            "hello",
+        );
+        assert!(response.contains("Connection: keep-alive\r\n"));
+        let response = send_and_read(&mut stream, b"POST /echo HTTP/1.0\r\nContent-Length: 5\r\n\r\nagain", "again");
+        assert!(response.starts_with("HTTP/1.0 200 OK"));
+    }
+
+    #[test]
+    fn http11_stays_open_by_default() {
+        let server = start_test_platform(HttpPlatform::new(echo_body));
+        let addr = server.addr();
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+
+        send_and_read(&mut stream, b"POST /echo HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nfirst", "first");
+        let response = send_and_read(
+            &mut stream,
+            b"POST /echo HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: 6\r\n\r\nsecond",
+            "second",
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+
+        // "Connection: close" ends the connection after the response
+        let mut rest = String::new();
+        stream.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "");
+    }
+
+    fn send_raw_bytes(addr: std::net::SocketAddr, request: &[u8]) -> (HttpResponse, Vec<u8>) {
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        stream.write_all(request).unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+
+        let head_end = response.windows(4).position(|window| window == b"\r\n\r\n").unwrap() + 4;
+        let head = read_http_response(std::str::from_utf8(&response[..head_end]).unwrap()).unwrap();
+        (head, response[head_end..].to_vec())
+    }
+
+    fn gzip_request(body: &str, accept_encoding: &str) -> Vec<u8> {
+        format!(
+            "POST /echo HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: {}\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+            accept_encoding,
+            body.len(),
+            body
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn compression_skips_small_bodies() {
+        let server = start_test_platform(HttpPlatform::new(echo_body).with_compression());
+        let addr = server.addr();
+
+        let (response, body) = send_raw_bytes(addr, &gzip_request("small body", "gzip, deflate"));
+        assert_eq!(response.headers.get("Content-Encoding"), None);
+        assert_eq!(body, b"small body");
+    }
+
+    #[test]
+    fn compression_of_large_bodies() {
+        let server = start_test_platform(HttpPlatform::new(echo_body).with_compression());
+        let addr = server.addr();
+        let text = "{\"name\": \"John Doe\", \"age\": 30}, ".repeat(100);
+
+        let (response, body) = send_raw_bytes(addr, &gzip_request(&text, "deflate, gzip;q=0.8"));
+        assert_eq!(
+            response.headers.get("Content-Encoding"),
+            Some(&KnownHeader::ContentEncoding("gzip".to_string()))
+        );
+        assert_eq!(response.headers.get("Content-Length"), Some(&KnownHeader::ContentLength(body.len())));
+        assert!(body.len() < text.len());
+        assert_eq!(crate::gzip::tests::gunzip(&body), text.as_bytes());
+
+        // gzip refused or not mentioned
+        let (response, body) = send_raw_bytes(addr, &gzip_request(&text, "gzip;q=0"));
+        assert_eq!(response.headers.get("Content-Encoding"), None);
+        assert_eq!(body, text.as_bytes());
+    }
+
+    #[test]
+    fn compression_threshold_is_configurable() {
+        let server = start_test_platform(HttpPlatform::new(echo_body).with_compression_threshold(8));
+        let addr = server.addr();
+
+        let (response, body) = send_raw_bytes(addr, &gzip_request("small body", "gzip"));
+        assert_eq!(
+            response.headers.get("Content-Encoding"),
+            Some(&KnownHeader::ContentEncoding("gzip".to_string()))
+        );
+        assert_eq!(crate::gzip::tests::gunzip(&body), b"small body");
+    }
+
+    #[test]
+    fn request_accepts_content_type() {
+        let request = |accept: &str| {
+            let request_str = format!("GET / HTTP/1.1\r\nHost: example.com\r\nAccept: {}\r\n\r\n", accept);
+            read_http_request(request_str.as_str()).unwrap()
+        };
+
+        let html = request("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8");
+        assert!(html.accepts(&HttpContentType::TextHtml));
+        assert!(html.accepts(&HttpContentType::ApplicationJson));
+
+        let text = request("text/*");
+        assert!(text.accepts(&HttpContentType::TextPlain));
+        assert!(!text.accepts(&HttpContentType::ApplicationJson));
+
+        let no_json = request("*/*, application/json;q=0");
+        assert!(!no_json.accepts(&HttpContentType::ApplicationJson));
+        assert!(no_json.accepts(&HttpContentType::ApplicationXml));
+
+        let without_accept = read_http_request("GET / HTTP/1.1\r\nHost: example.com\r\n\r\n").unwrap();
+        assert!(without_accept.accepts(&HttpContentType::ApplicationJson));
+    }
+
+    #[test]
+    fn handle_request_too_many_headers() {
+        let server = start_test_platform(HttpPlatform::new(echo_body).with_max_headers(10));
+        let addr = server.addr();
+        let request = |extra_headers: usize| {
+            format!("GET / HTTP/1.1\r\nHost: localhost\r\n{}\r\n", "X-Header: 1\r\n".repeat(extra_headers))
+        };
+
+        let response = read_http_response(send_raw(addr, request(10).as_bytes()).as_str()).unwrap();
+        assert_eq!(response.status_code, HttpStatusCode::RequestHeaderFieldsTooLarge);
+        assert_eq!(response.body, Some(HttpBody::Text("Too many headers".to_string())));
+
+        // exactly at the limit is fine, repeated names count every time
+        let response = read_http_response(send_raw(addr, request(9).as_bytes()).as_str()).unwrap();
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+    }
+
+    #[test]
+    fn handle_request_too_many_headers_while_client_keeps_sending() {
+        let server = start_test_platform(HttpPlatform::new(echo_body).with_max_headers(10));
+        let body_length = 256 * 1024;
+        let mut request = format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\n{}Content-Length: {}\r\n\r\n",
+            "X-Header: 1\r\n".repeat(10),
+            body_length
+        )
+        .into_bytes();
+        request.extend(std::iter::repeat_n(b'a', body_length));
+
+        // the body is still on its way while the 431 is read, unread input would reset the
+        // connection and could take the response with it
+        let mut stream = std::net::TcpStream::connect(server.addr()).unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        let sender = std::thread::spawn(move || {
+            let _ = writer.write_all(&request);
+        });
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        sender.join().unwrap();
+
+        let response = read_http_response(response.as_str()).unwrap();
+        assert_eq!(response.status_code, HttpStatusCode::RequestHeaderFieldsTooLarge);
+    }
+
+    #[test]
+    fn handle_request_head_too_large() {
+        let server = start_test_platform(HttpPlatform::new(echo_body).with_max_header_bytes(256));
+        let addr = server.addr();
+
+        let request = format!("GET / HTTP/1.1\r\nHost: localhost\r\nCookie: {}\r\n\r\n", "a".repeat(300));
+        let response = read_http_response(send_raw(addr, request.as_bytes()).as_str()).unwrap();
+        assert_eq!(response.status_code, HttpStatusCode::RequestHeaderFieldsTooLarge);
+
+        // a head that never ends is cut off as well
+        let request = format!("GET / HTTP/1.1\r\nHost: localhost\r\n{}", "X-Header: 1\r\n".repeat(100));
+        let response = read_http_response(send_raw(addr, request.as_bytes()).as_str()).unwrap();
+        assert_eq!(response.status_code, HttpStatusCode::RequestHeaderFieldsTooLarge);
+    }
+
+    #[test]
+    fn read_http_request_line_too_long() {
+        let request_str = format!("GET /search?q={} HTTP/1.1\r\nHost: example.com\r\n\r\n", "a".repeat(100));
+        assert!(matches!(
+            read_http_request_with_limit(&request_str, 64),
+            Err(HttpRequestError::RequestLineTooLong(123))
+        ));
+        assert!(read_http_request_with_limit(&request_str, 123).is_ok());
+    }
+
+    #[test]
+    fn handle_request_uri_too_long() {
+        let server = start_test_platform(HttpPlatform::new(echo_body).with_max_request_line(1024));
+        let addr = server.addr();
+
+        let request = format!("GET /search?q={} HTTP/1.1\r\nHost: localhost\r\n\r\n", "a".repeat(2000));
+        let response = read_http_response(send_raw(addr, request.as_bytes()).as_str()).unwrap();
+        assert_eq!(response.status_code, HttpStatusCode::UriTooLong);
+
+        // a target longer than the whole head may be is still reported as 414
+        let request = format!("GET /search?q={} HTTP/1.1\r\nHost: localhost\r\n\r\n", "a".repeat(20_000));
+        let response = read_http_response(send_raw(addr, request.as_bytes()).as_str()).unwrap();
+        assert_eq!(response.status_code, HttpStatusCode::UriTooLong);
+
+        let request = format!("GET /search?q={} HTTP/1.1\r\nHost: localhost\r\n\r\n", "a".repeat(500));
+        let response = read_http_response(send_raw(addr, request.as_bytes()).as_str()).unwrap();
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+    }
+
+    #[test]
+    fn request_if_match() {
+        let request = |if_match: &str| {
+            let request_str = format!("PUT /people/1 HTTP/1.1\r\nHost: example.com\r\nIf-Match: {}\r\n\r\n", if_match);
+            read_http_request(request_str.as_str()).unwrap()
+        };
+
+        let matching = request("\"v1\", \"v2\"");
+        assert_eq!(matching.header("If-Match"), Some(&KnownHeader::IfMatch("\"v1\", \"v2\"".to_string())));
+        assert!(matching.check_if_match("\"v2\"").is_ok());
+        assert!(request("*").check_if_match("\"v3\"").is_ok());
+
+        let response = request("\"v1\"").check_if_match("\"v2\"").unwrap_err();
+        assert_eq!(response.status_code, HttpStatusCode::PreconditionFailed);
+        assert_eq!(response.headers.get("ETag"), Some(&KnownHeader::Other("\"v2\"".to_string())));
+        assert!(request("W/\"v2\"").check_if_match("W/\"v2\"").is_err());
+
+        // without If-Match the change is unconditional
+        let request = read_http_request("PUT /people/1 HTTP/1.1\r\nHost: example.com\r\n\r\n").unwrap();
+        assert!(request.check_if_match("\"v2\"").is_ok());
+    }
+
+    #[test]
+    fn host_header_without_port() {
+        let header = KnownHeader::from_str("Host", "example.com");
+        assert_eq!(header.host_and_port(), Some(("example.com".to_string(), None)));
+    }
+
+    #[test]
+    fn host_header_with_port() {
+        let header = KnownHeader::from_str("Host", "example.com:8080");
+        assert_eq!(header.host_and_port(), Some(("example.com".to_string(), Some(8080))));
+    }
+
+    #[test]
+    fn host_header_with_ipv6_and_port() {
+        let header = KnownHeader::from_str("Host", "[::1]:8080");
+        assert_eq!(header.host_and_port(), Some(("::1".to_string(), Some(8080))));
+
+        let header = KnownHeader::from_str("Host", "[::1]");
+        assert_eq!(header.host_and_port(), Some(("::1".to_string(), None)));
+    }
+
+    #[test]
+    fn host_and_port_on_other_header() {
+        let header = KnownHeader::from_str("Accept", "*/*");
+        assert_eq!(header.host_and_port(), None);
+    }
+
+    #[test]
+    fn handle_request_expect_continue() {
+        let server = start_test_platform(HttpPlatform::new(echo_body));
+        let addr = server.addr();
+
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"POST /upload HTTP/1.1\r\nHost: example.com\r\nExpect: 100-continue\r\nContent-Length: 5\r\n\r\n")
+            .unwrap();
+
+        let mut interim = [0; 25];
+        stream.read_exact(&mut interim).unwrap();
+        assert_eq!(&interim, b"HTTP/1.1 100 Continue\r\n\r\n");
+
+        stream.write_all(b"hello").unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let response = read_http_response(response.as_str()).unwrap();
+
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+        assert_eq!(response.body, Some(HttpBody::Text("hello".to_string())));
+    }
+
+    #[test]
+    fn handle_request_expect_continue_rejected() {
+        let server = start_test_platform(HttpPlatform::new(echo_body).with_max_body_size(16));
+        let addr = server.addr();
+
+        let response = send_raw(
+            addr,
+            b"POST /upload HTTP/1.1\r\nHost: example.com\r\nExpect: 100-continue\r\nContent-Length: 100\r\n\r\n",
+        );
+
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+    }
+
+    #[test]
+    fn query_as_integer() {
+        let path = HttpPath::from_str("/items?page=3");
+
+        assert_eq!(path.query_as::<usize>("page"), Some(Ok(3)));
+        assert_eq!(path.query_as::<usize>("size"), None);
+    }
+
+    #[test]
+    fn query_as_float() {
+        let path = HttpPath::from_str("/items?ratio=0.75");
+
+        assert_eq!(path.query_as::<f64>("ratio"), Some(Ok(0.75)));
+    }
+
+    #[test]
+    fn query_as_non_numeric_value() {
+        let path = HttpPath::from_str("/items?page=abc");
+
+        assert!(matches!(path.query_as::<usize>("page"), Some(Err(_))));
+    }
+
+    #[test]
+    fn handle_request_metrics_keep_alive() {
+        let platform = HttpPlatform::new(echo_body);
+        let server = start_test_platform(platform.clone());
+        let addr = server.addr();
+
+        let request = "GET / HTTP/1.1\r\nHost: example.com\r\n\r\n".repeat(3);
+        let response = send_raw(addr, request.as_bytes());
+        assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 3);
+
+        let metrics = platform.metrics();
+        assert_eq!(metrics.connections, 1);
+        assert_eq!(metrics.requests, 3);
+        assert_eq!(metrics.max_requests_per_connection, 3);
+        assert_eq!(metrics.requests_per_connection(), 3.0);
+    }
+
+    #[test]
+    fn normalized_path_collapses_slashes() {
+        let path = HttpPath::from_str("/a//b");
+        assert_eq!(path.normalized_path(), "/a/b");
+        assert_eq!(path.full_path, "/a//b");
+
+        assert_eq!(HttpPath::from_str("//a///b/").normalized_path(), "/a/b/");
+        assert_eq!(HttpPath::from_str("").normalized_path(), "/");
+    }
+
+    #[test]
+    fn normalized_path_resolves_dot_segments() {
+        assert_eq!(HttpPath::from_str("/a/./b").normalized_path(), "/a/b");
+        assert_eq!(HttpPath::from_str("/a/../b").normalized_path(), "/b");
+        assert_eq!(HttpPath::from_str("/a/b/..").normalized_path(), "/a/");
+    }
+
+    #[test]
+    fn normalized_path_stays_below_root() {
+        assert_eq!(HttpPath::from_str("/../../etc/passwd").normalized_path(), "/etc/passwd");
+        assert_eq!(HttpPath::from_str("/..").normalized_path(), "/");
+    }
+
+    struct Person {
+        name: String,
+        age: i64,
+        courses: Vec<String>,
+    }
+
+    impl FromJson for Person {
+        fn from_json(json: &crate::json::JsonType) -> Self {
+            use crate::json::JsonType;
+
+            let (name, age, courses) = match json {
+                JsonType::Object(obj) => (obj.get("name"), obj.get("age"), obj.get("courses")),
+                _ => (None, None, None),
+            };
+
+            Person {
+                name: match name {
+                    Some(JsonType::String(s)) => s.clone(),
+                    _ => "".to_string(),
+                },
+                age: match age {
+                    Some(JsonType::Number(n)) => *n,
+                    _ => 0,
+                },
+                courses: match courses {
+                    Some(JsonType::Array(arr)) => arr
+                        .iter()
+                        .filter_map(|item| match item {
+                            JsonType::String(s) => Some(s.clone()),
+                            _ => None,
+                        })
+                        .collect(),
+                    _ => vec![],
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn response_json_round_trip() {
+        let value = parse_json(r#"{"name": "John Doe", "age": 30, "courses": ["Math", "Science"]}"#).unwrap();
+
+        let response = HttpResponse::json(HttpStatusCode::Created, &value);
+        let expected_length = response.body.as_ref().unwrap().len();
+        let response = read_http_response(write_http_response(response).unwrap().as_str()).unwrap();
+
+        assert_eq!(response.status_code, HttpStatusCode::Created);
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some(&KnownHeader::ContentType(HttpContentType::ApplicationJson))
+        );
+        assert_eq!(
+            response.headers.get("Content-Length"),
+            Some(&KnownHeader::ContentLength(expected_length))
+        );
+        assert_eq!(parse_json(response.body.unwrap().as_text().unwrap()), Ok(value));
+    }
+
+    #[test]
+    fn request_json_body() {
+        let request_str = "POST /people HTTP/1.1\r\nHost: example.com\r\nContent-Type: application/json\r\n\r\n\
+            {\"name\": \"John Doe\", \"age\": 30, \"courses\": [\"Math\", \"Science\"]}";
+        let request = read_http_request(request_str).unwrap();
+
+        let person: Person = request.json().unwrap();
+        assert_eq!(person.name, "John Doe");
+        assert_eq!(person.age, 30);
+        assert_eq!(person.courses, vec!["Math".to_string(), "Science".to_string()]);
+    }
+
+    #[test]
+    fn request_json_wrong_content_type() {
+        let request_str = "POST /people HTTP/1.1\r\nHost: example.com\r\nContent-Type: text/plain\r\n\r\n{}";
+        let request = read_http_request(request_str).unwrap();
+
+        assert!(matches!(request.json::<Person>(), Err(ParserError::NotSupported(_))));
+    }
+
+    #[test]
+    fn request_json_missing_body() {
+        let request_str = "POST /people HTTP/1.1\r\nHost: example.com\r\nContent-Type: application/json\r\n\r\n";
+        let request = read_http_request(request_str).unwrap();
+
+        assert!(matches!(request.json::<Person>(), Err(ParserError::EmptyInput)));
+    }
+
+    // JSON errors are the handler's to report, the platform only rejects bodies that aren't text
+    fn create_person(req: HttpRequest) -> HttpResponse {
+        let (status_code, body) = match req.json::<Person>() {
+            Ok(person) => (HttpStatusCode::Created, person.name),
+            Err(e) => (HttpStatusCode::BadRequest, format!("Invalid JSON: {:?}", e)),
+        };
+
+        let mut headers = HashMap::new();
+        headers.insert("Content-Length".to_string(), KnownHeader::ContentLength(body.len()));
+
+        HttpResponse {
+            version: req.version,
+            status_code,
+            headers,
+            cookies: Vec::new(),
+            body: Some(HttpBody::Text(body)),
+        }
+    }
+
+    #[test]
+    fn handle_request_invalid_utf8_body() {
+        let server = start_test_platform(HttpPlatform::new(create_person));
+        let addr = server.addr();
+
+        let mut request = b"POST /people HTTP/1.1\r\nHost: example.com\r\nContent-Type: application/json\r\nContent-Length: 4\r\n\r\n".to_vec();
+        request.extend_from_slice(&[b'{', 0xff, 0xfe, b'}']);
+        let response = send_raw(addr, &request);
+        let response = read_http_response(response.as_str()).unwrap();
+
+        assert_eq!(response.status_code, HttpStatusCode::BadRequest);
+        assert_eq!(response.body, Some(HttpBody::Text("Request body is not valid UTF-8".to_string())));
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some(&KnownHeader::ContentType(HttpContentType::TextPlain))
+        );
+    }
+
+    #[test]
+    fn handle_request_malformed_request_explains_error() {
+        let server = start_test_platform(HttpPlatform::new(echo_body));
+        let addr = server.addr();
+
+        let response = send_raw(addr, b"F(O /echo HTTP/1.1\r\nHost: example.com\r\n\r\n");
+        let response = read_http_response(response.as_str()).unwrap();
+
+        assert_eq!(response.status_code, HttpStatusCode::BadRequest);
+        assert_eq!(response.body, Some(HttpBody::Text("Invalid method: F(O".to_string())));
+        assert_eq!(
+            response.headers.get("Content-Length"),
+            Some(&KnownHeader::ContentLength("Invalid method: F(O".len()))
+        );
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some(&KnownHeader::ContentType(HttpContentType::TextPlain))
+        );
+    }
+
+    #[test]
+    fn handle_request_malformed_request_closes_connection() {
+        let server = start_test_platform(HttpPlatform::new(|req: HttpRequest| {
+            HttpResponse::text(HttpStatusCode::OK, &format!("served {}", req.path.path))
+        }));
+        let addr = server.addr();
+
+        let smuggled = "GET /smuggled HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let request = format!(
+            "POST /echo HTTP/1.1\r\nHost: example.com\r\n Folded: x\r\nContent-Length: {}\r\n\r\n{}",
+            smuggled.len(),
+            smuggled
+        );
+        let response = send_raw(addr, request.as_bytes());
+
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"), "{}", response);
+        assert_eq!(response.matches("HTTP/1.1 ").count(), 1, "{}", response);
+        assert!(!response.contains("served"), "{}", response);
+    }
+
+    #[test]
+    fn handle_request_default_content_type() {
+        let server = start_test_platform(HttpPlatform::new(echo_body));
+        let addr = server.addr();
+
+        let response = send_raw(addr, b"POST /echo HTTP/1.1\r\nHost: example.com\r\nContent-Length: 5\r\n\r\nhello");
+        assert!(response.contains("\r\nContent-Type: text/plain\r\n"));
+
+        let platform = HttpPlatform::new(echo_body).with_default_content_type(HttpContentType::TextHtml);
+        let server = start_test_platform(platform);
+        let addr = server.addr();
+
+        let response = send_raw(addr, b"POST /echo HTTP/1.1\r\nHost: example.com\r\nContent-Length: 5\r\n\r\nhello");
+        assert!(response.contains("\r\nContent-Type: text/html\r\n"));
+
+        let echo_json = |request: HttpRequest| {
+            let value = parse_json(request.body.unwrap_or_default().as_str()).unwrap();
+            HttpResponse::json(HttpStatusCode::OK, &value)
+        };
+        let server = start_test_platform(HttpPlatform::new(echo_json));
+        let addr = server.addr();
+        let response = send_raw(addr, b"POST /echo HTTP/1.1\r\nHost: example.com\r\nContent-Length: 2\r\n\r\n{}");
+        assert!(response.contains("\r\nContent-Type: application/json\r\n"));
+        assert!(!response.contains("text/plain"));
+    }
+
+    #[test]
+    fn handle_request_lone_surrogate_in_json_body() {
+        let server = start_test_platform(HttpPlatform::new(create_person));
+        let addr = server.addr();
+
+        let body = r#"{"name": "\ud800"}"#;
+        let request = format!(
+            "POST /people HTTP/1.1\r\nHost: example.com\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let response = send_raw(addr, request.as_bytes());
+        let response = read_http_response(response.as_str()).unwrap();
+
+        assert_eq!(response.status_code, HttpStatusCode::BadRequest);
+        assert!(response.body.unwrap().as_text().unwrap().starts_with("Invalid JSON: InvalidSyntax"));
+    }
+
+    #[test]
+    fn handle_request_incomplete_head_times_out() {
+        let platform = HttpPlatform::new(echo_body).with_read_timeout(Duration::from_millis(100));
+        let server = start_test_platform(platform);
+        let addr = server.addr();
+
+        // the connection stays open for writing, the server has to give up on its own
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: exa").unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let response = read_http_response(response.as_str()).unwrap();
+
+        assert_eq!(response.status_code, HttpStatusCode::RequestTimeout);
+    }
+
+    #[test]
+    fn handle_request_complete_request_before_timeout() {
+        let platform = HttpPlatform::new(echo_body).with_read_timeout(Duration::from_millis(100));
+        let server = start_test_platform(platform);
+        let addr = server.addr();
+
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        stream.write_all(b"POST / HTTP/1.1\r\nHost: example.com\r\nContent-Length: 5\r\n\r\nhello").unwrap();
+
+        // the idle connection is closed after the timeout without a 408
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert_eq!(response.matches("HTTP/1.1").count(), 1);
+        let response = read_http_response(response.as_str()).unwrap();
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+        assert_eq!(response.body, Some(HttpBody::Text("hello".to_string())));
+    }
+
+    fn send_in_parts(addr: std::net::SocketAddr, parts: &[&[u8]]) -> String {
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        stream.set_nodelay(true).unwrap();
+
+        for part in parts {
+            stream.write_all(part).unwrap();
+            stream.flush().unwrap();
+            // give the server a chance to read each part on its own
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn handle_request_body_in_separate_read() {
+        let server = start_test_platform(HttpPlatform::new(echo_body));
+        let addr = server.addr();
+
+        let response = send_in_parts(
+            addr,
+            &[
+                b"POST /upload HTTP/1.1\r\nHost: example.com\r\nContent-Length: 11\r\n\r\n",
+                b"hello world",
+            ],
+        );
+        let response = read_http_response(response.as_str()).unwrap();
+
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+        assert_eq!(response.body, Some(HttpBody::Text("hello world".to_string())));
+    }
+
+    #[test]
+    fn handle_request_blank_line_split_across_reads() {
+        let server = start_test_platform(HttpPlatform::new(echo_body));
+        let addr = server.addr();
+
+        let response = send_in_parts(
+            addr,
+            &[
+                b"POST /upload HTTP/1.1\r\nHost: example.com\r\nContent-Length: 11\r\n\r",
+                b"\nhello",
+                b" world",
+            ],
+        );
+        let response = read_http_response(response.as_str()).unwrap();
+
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+        assert_eq!(response.body, Some(HttpBody::Text("hello world".to_string())));
+    }
+
+    #[test]
+    fn read_http_request_with_transfer_encoding() {
+        let request_str = "POST /upload HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: chunked\r\nContent-Encoding: gzip\r\n\r\n";
+        let request = read_http_request(request_str).unwrap();
+
+        assert_eq!(
+            request.headers.get("Transfer-Encoding"),
+            Some(&KnownHeader::TransferEncoding("chunked".to_string()))
+        );
+        assert_eq!(
+            request.headers.get("Content-Encoding"),
+            Some(&KnownHeader::ContentEncoding("gzip".to_string()))
+        );
+    }
+
+    #[test]
+    fn content_type_with_parameters() {
+        assert_eq!(HttpContentType::from_str("application/json; charset=utf-8"), HttpContentType::ApplicationJson);
+        assert_eq!(HttpContentType::from_str("text/html; charset=UTF-8"), HttpContentType::TextHtml);
+        assert_eq!(HttpContentType::from_str("Text/Plain;charset=us-ascii"), HttpContentType::TextPlain);
+        assert_eq!(HttpContentType::from_str("image/png; q=1"), HttpContentType::Other("image/png".to_string()));
+
+        // unknown types lose their parameters and case too, so they compare like known ones
+        let (content_type, params) = HttpContentType::with_params("Text/CSV; charset=utf-8");
+        assert_eq!(content_type, HttpContentType::Other("text/csv".to_string()));
+        assert_eq!(params.get("charset").map(String::as_str), Some("utf-8"));
+        assert_eq!(HttpContentType::from_str("text/csv"), content_type);
+
+        let request = read_http_request(
+            "POST /people HTTP/1.1\r\nHost: example.com\r\nContent-Type: application/json; charset=utf-8\r\n\r\n{\"name\": \"John\"}",
+        )
+        .unwrap();
+        assert_eq!(
+            request.header("Content-Type"),
+            Some(&KnownHeader::ContentType(HttpContentType::ApplicationJson))
+        );
+    }
+
+    #[test]
+    fn content_type_multipart_boundary() {
+        let (content_type, params) =
+            HttpContentType::with_params("multipart/form-data; boundary=\"----WebKitFormBoundary7MA4YWxk\"; Charset=utf-8");
+        assert_eq!(content_type, HttpContentType::MultipartFormData);
+        assert_eq!(params.get("boundary").map(String::as_str), Some("----WebKitFormBoundary7MA4YWxk"));
+        assert_eq!(params.get("charset").map(String::as_str), Some("utf-8"));
+
+        let request = read_http_request(
+            "POST /upload HTTP/1.1\r\nHost: example.com\r\nContent-Type: multipart/form-data; boundary=abc123\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(
+            request.header("Content-Type"),
+            Some(&KnownHeader::ContentType(HttpContentType::MultipartFormData))
+        );
+        assert_eq!(request.content_type_params.get("boundary").map(String::as_str), Some("abc123"));
+    }
+
+    #[test]
+    fn read_form_fields() {
+        let body = "name=John+Doe&course=Math&course=Science&note=caf%C3%A9%20%26%20more";
+        let request = read_http_request(&format!(
+            "POST /signup HTTP/1.1\r\nHost: example.com\r\nContent-Type: application/x-www-form-urlencoded; charset=utf-8\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        ))
+        .unwrap();
+
+        let form = request.form().unwrap();
+        assert_eq!(form["name"], vec!["John Doe"]);
+        assert_eq!(form["course"], vec!["Math", "Science"]);
+        assert_eq!(form["note"], vec!["caf\u{e9} & more"]);
+
+        let json = read_http_request("POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}").unwrap();
+        assert!(matches!(json.form(), Err(HttpRequestError::InvalidHeader(_))));
+
+        let untyped = read_http_request("POST / HTTP/1.1\r\nContent-Length: 3\r\n\r\na=1").unwrap();
+        assert!(matches!(untyped.form(), Err(HttpRequestError::InvalidHeader(_))));
+
+        let no_body = read_http_request("POST / HTTP/1.1\r\nContent-Type: application/x-www-form-urlencoded\r\n\r\n").unwrap();
+        assert_eq!(no_body.form(), Err(HttpRequestError::InvalidRequest("Missing form body".to_string())));
+    }
+
+    #[test]
+    fn missing_request_line_is_rejected() {
+        let missing = Some(HttpRequestError::InvalidRequest("Missing request line".to_string()));
+
+        for input in ["", "\r\n", "\r\n\r\n", "   \t\n  "] {
+            assert_eq!(read_http_request(input).err(), missing, "{:?}", input);
+        }
+        assert_eq!(read_http_request_with_limit("\r\n", 10).err(), missing);
+        assert!(read_http_request_buf(&mut "\r\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn zero_content_length_is_an_empty_body() {
+        let post = "POST /submit HTTP/1.1\r\nHost: example.com\r\nContent-Length: 0\r\n\r\n";
+        assert_eq!(read_http_request(post).unwrap().body, Some(String::new()));
+        assert_eq!(read_http_request_buf(&mut post.as_bytes()).unwrap().body, Some(String::new()));
+
+        let (request, rest) = read_http_request_partial(post).unwrap();
+        assert_eq!((request.body, rest), (Some(String::new()), ""));
+
+        // without a Content-Length there is no body at all
+        let get = "GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        assert_eq!(read_http_request(get).unwrap().body, None);
+
+        let platform = HttpPlatform::new(|request: HttpRequest| {
+            HttpResponse::text(HttpStatusCode::OK, &format!("{:?}", request.body))
+        });
+        let server = start_test_platform(platform);
+        let response = send_raw(server.addr(), post.replace("HTTP/1.1\r\n", "HTTP/1.1\r\nConnection: close\r\n").as_bytes());
+        assert!(response.ends_with("Some(\"\")"), "{}", response);
+    }
+
+    #[test]
+    fn normalize_list_header_whitespace() {
+        let mut request = read_http_request(
+            "GET / HTTP/1.1\r\nHost: example.com\r\nAccept: text/html ,\t application/json;  q=0.9 ,  */*;q=0.1\r\nIf-Match: \"a  b\",   \"c\"\r\nAuthorization: Basic   abc\r\nCookie: a=1;   b=2\r\n\r\n",
+        )
+        .unwrap();
+        request.normalize_header_whitespace();
+
+        assert_eq!(
+            request.header("Accept"),
+            Some(&KnownHeader::Accept("text/html , application/json; q=0.9 , */*;q=0.1".to_string()))
+        );
+        assert_eq!(request.header("If-Match"), Some(&KnownHeader::IfMatch("\"a  b\", \"c\"".to_string())));
+        assert_eq!(request.header("Authorization"), Some(&KnownHeader::Authorization("Basic   abc".to_string())));
+        assert_eq!(request.header("Cookie"), Some(&KnownHeader::Cookie("a=1;   b=2".to_string())));
+        assert!(request.raw_headers.contains(&("Accept".to_string(), "text/html ,\t application/json;  q=0.9 ,  */*;q=0.1".to_string())));
+    }
+
+    #[test]
+    fn cache_control_directives() {
+        let request = read_http_request(
+            "GET / HTTP/1.1\r\nHost: example.com\r\nCache-Control: max-age=3600, no-cache, Private, must-revalidate, community=\"UCI\"\r\nExpires: Thu, 01 Dec 1994 16:00:00 GMT\r\n\r\n",
+        )
+        .unwrap();
+
+        let cache_control = request.header("Cache-Control").and_then(KnownHeader::cache_control).unwrap();
+        assert_eq!(
+            cache_control,
+            CacheControl {
+                max_age: Some(3600),
+                no_cache: true,
+                private: true,
+                must_revalidate: true,
+                ..CacheControl::default()
+            }
+        );
+        assert_eq!(
+            request.header("Expires"),
+            Some(&KnownHeader::Expires(HttpDate::parse("Thu, 01 Dec 1994 16:00:00 GMT").unwrap()))
+        );
+
+        let cache_control = CacheControl::parse("public, s-maxage=\"600\", max-age=soon, no-store");
+        assert!(cache_control.public && cache_control.no_store && !cache_control.private);
+        assert_eq!((cache_control.max_age, cache_control.s_maxage), (None, Some(600)));
+
+        assert_eq!(KnownHeader::Host("example.com".to_string()).cache_control(), None);
+    }
+
+    #[test]
+    fn date_headers_are_typed() {
+        let request = read_http_request(
+            "GET / HTTP/1.1\r\nHost: example.com\r\nIf-Modified-Since: Sunday, 06-Nov-94 08:49:37 GMT\r\nExpires: 0\r\n\r\n",
+        )
+        .unwrap();
+
+        let date = HttpDate::from_unix_seconds(784111777);
+        assert_eq!(request.header("If-Modified-Since"), Some(&KnownHeader::IfModifiedSince(date)));
+        assert_eq!(request.header("Expires"), Some(&KnownHeader::Other("0".to_string())));
+
+        let mut response = HttpResponse::text(HttpStatusCode::OK, "");
+        response.headers.insert("Date".to_string(), KnownHeader::Date(date));
+        let written = write_http_response(response).unwrap();
+        assert!(written.contains("Date: Sun, 06 Nov 1994 08:49:37 GMT\r\n"));
+    }
+
+    #[test]
+    fn raw_headers_keep_order_and_casing() {
+        let request = read_http_request(
+            "GET / HTTP/1.1\r\nhost: example.com\r\nX-Forwarded-For: 10.0.0.1\r\nAccept: */*\r\nx-forwarded-for: 10.0.0.2\r\nX-Empty:\r\n\r\n",
+        )
+        .unwrap();
+
+        let expected = [
+            ("host", "example.com"),
+            ("X-Forwarded-For", "10.0.0.1"),
+            ("Accept", "*/*"),
+            ("x-forwarded-for", "10.0.0.2"),
+            ("X-Empty", ""),
+        ];
+        let raw: Vec<(&str, &str)> = request.raw_headers.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+        assert_eq!(raw, expected);
+    }
+
+    #[test]
+    fn write_http_response_empty_bodies() {
+        let empty = |status_code| HttpResponse {
+            version: HttpVersion::HTTP11,
+            status_code,
+            headers: HashMap::new(),
+            cookies: Vec::new(),
+            body: None,
+        };
+
+        assert_eq!(write_http_response(empty(HttpStatusCode::OK)).unwrap(), "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+        assert_eq!(write_http_response(empty(HttpStatusCode::NoContent)).unwrap(), "HTTP/1.1 204 No Content\r\n\r\n");
+        assert_eq!(write_http_response(empty(HttpStatusCode::NotModified)).unwrap(), "HTTP/1.1 304 Not Modified\r\n\r\n");
+
+        // a body or length set on a 204 is dropped
+        let response = HttpResponse::text(HttpStatusCode::NoContent, "ignored");
+        assert_eq!(
+            write_http_response(response).unwrap(),
+            "HTTP/1.1 204 No Content\r\nContent-Type: text/plain\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn write_http_response_with_encodings() {
+        let mut headers = HashMap::new();
+        headers.insert("Transfer-Encoding".to_string(), KnownHeader::TransferEncoding("chunked".to_string()));
+        headers.insert("Content-Encoding".to_string(), KnownHeader::ContentEncoding("gzip".to_string()));
+
+        let response = HttpResponse {
+            version: HttpVersion::HTTP11,
+            status_code: HttpStatusCode::OK,
+            headers,
+            cookies: Vec::new(),
+            body: None,
+        };
+
+        assert_eq!(
+            write_http_response(response).unwrap(),
+            "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nTransfer-Encoding: chunked\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn read_http_request_with_origin() {
+        let request_str = "POST /submit HTTP/1.1\r\nHost: example.com\r\nOrigin: https://example.com\r\n\r\n";
+        let request = read_http_request(request_str).unwrap();
+
+        assert_eq!(
+            request.headers.get("Origin"),
+            Some(&KnownHeader::Origin("https://example.com".to_string()))
+        );
+        assert_eq!(request.origin(), Some("https://example.com"));
+    }
+
+    #[test]
+    fn read_http_request_with_origin_and_referer() {
+        let request_str = "POST /submit HTTP/1.1\r\nHost: example.com\r\norigin: https://example.com\r\nReferer: https://example.com/form\r\n\r\n";
+        let request = read_http_request(request_str).unwrap();
+
+        assert_eq!(request.origin(), Some("https://example.com"));
+        assert_eq!(
+            request.header("Referer"),
+            Some(&KnownHeader::Referer("https://example.com/form".to_string()))
+        );
+
+        let request = read_http_request("GET / HTTP/1.1\r\nHost: example.com\r\n\r\n").unwrap();
+        assert_eq!(request.origin(), None);
+    }
+
+    #[test]
+    fn only_rejects_other_methods() {
+        let handler = only(HttpMethod::GET, echo_body);
+
+        let request = read_http_request("GET / HTTP/1.1\r\nHost: example.com\r\n\r\n").unwrap();
+        let response = handler(request);
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+
+        let request = read_http_request("POST / HTTP/1.1\r\nHost: example.com\r\n\r\nhello").unwrap();
+        let response = handler(request);
+        assert_eq!(response.status_code, HttpStatusCode::MethodNotAllowed);
+        assert_eq!(response.headers.get("Allow"), Some(&KnownHeader::Allow("GET".to_string())));
+        assert_eq!(response.body, None);
+    }
 }
@@ -1,79 +1,588 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ops::Index;
 
 // A simple representation of JSON parsing errors
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum ParserError {
     UnexpectedToken(String),
     InvalidSyntax(String),
     MissingToken(String),
     EmptyInput,
-    NotSupported(String)
+    NotSupported(String),
+    PatchFailed(String)
 }
 
 // A simple representation of JSON values
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum JsonType {
     Object(HashMap<String, JsonType>),
     Array(Vec<JsonType>),
     String(String),
     Number(i64),
     Decimal(f64),
-    Boolean(bool)
+    Boolean(bool),
+    Null
 }
 
 pub trait FromJson {
     fn from_json(json: &JsonType) -> Self;
 }
 
-pub fn parse_json(mut input: &str) -> Result<JsonType, ParserError>  {
-    if input.trim().is_empty() {
-        return Err(ParserError::EmptyInput);
+impl JsonType {
+    // Member of an object, None for a missing key or when self is not an object
+    pub fn get(&self, key: &str) -> Option<&JsonType> {
+        match self {
+            JsonType::Object(map) => map.get(key),
+            _ => None,
+        }
     }
 
-    input = &input.trim_start();
-    
-    match input.chars().nth(0).unwrap() {
+    // Element of an array, None when out of bounds or when self is not an array
+    pub fn get_index(&self, index: usize) -> Option<&JsonType> {
+        match self {
+            JsonType::Array(arr) => arr.get(index),
+            _ => None,
+        }
+    }
+
+    // Resolves a JSON Pointer (RFC 6901) such as "/address/city" or "/courses/0"
+    pub fn pointer(&self, pointer: &str) -> Option<&JsonType> {
+        let tokens = split_pointer(pointer).ok()?;
+        let mut current = self;
+
+        for token in tokens.iter() {
+            current = match current {
+                JsonType::Object(map) => map.get(token)?,
+                JsonType::Array(arr) => arr.get(parse_index(token)?)?,
+                _ => return None,
+            };
+        }
+
+        Some(current)
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut JsonType> {
+        let tokens = split_pointer(pointer).ok()?;
+        resolve_mut(self, &tokens)
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonType::Null)
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(self, JsonType::Object(_))
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self, JsonType::Array(_))
+    }
+
+    pub fn is_string(&self) -> bool {
+        matches!(self, JsonType::String(_))
+    }
+
+    // Only the integer variant, see is_decimal for floating point values
+    pub fn is_number(&self) -> bool {
+        matches!(self, JsonType::Number(_))
+    }
+
+    pub fn is_decimal(&self) -> bool {
+        matches!(self, JsonType::Decimal(_))
+    }
+
+    pub fn is_boolean(&self) -> bool {
+        matches!(self, JsonType::Boolean(_))
+    }
+
+    // JSON has a single number type, so both numeric variants convert to f64
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonType::Number(n) => Some(*n as f64),
+            JsonType::Decimal(d) => Some(*d),
+            _ => None,
+        }
+    }
+
+    // A Decimal converts only when it has no fractional part and fits into an i64
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            JsonType::Number(n) => Some(*n),
+            JsonType::Decimal(d) if d.fract() == 0.0 && *d >= i64::MIN as f64 && *d < i64::MAX as f64 => Some(*d as i64),
+            _ => None,
+        }
+    }
+
+    // Returns the value previously stored under key, fails for anything but an object
+    pub fn insert(&mut self, key: String, value: JsonType) -> Result<Option<JsonType>, ParserError> {
+        match self {
+            JsonType::Object(map) => Ok(map.insert(key, value)),
+            _ => Err(ParserError::NotSupported(format!("Cannot insert key '{}' into a non-object", key))),
+        }
+    }
+
+    // None when the key is missing or self isn't an object
+    pub fn remove(&mut self, key: &str) -> Option<JsonType> {
+        match self {
+            JsonType::Object(map) => map.remove(key),
+            _ => None,
+        }
+    }
+
+    pub fn push(&mut self, value: JsonType) -> Result<(), ParserError> {
+        match self {
+            JsonType::Array(arr) => {
+                arr.push(value);
+                Ok(())
+            }
+            _ => Err(ParserError::NotSupported("Cannot push onto a non-array".to_string())),
+        }
+    }
+
+    // Moves the value out and leaves null behind, so a subtree can be transformed
+    // without cloning it
+    pub fn take(&mut self) -> JsonType {
+        std::mem::replace(self, JsonType::Null)
+    }
+
+    pub fn replace(&mut self, value: JsonType) -> JsonType {
+        std::mem::replace(self, value)
+    }
+
+    // Entries of an object, elements of an array, characters (not bytes) of a
+    // string, scalars have no length
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            JsonType::Object(map) => Some(map.len()),
+            JsonType::Array(arr) => Some(arr.len()),
+            JsonType::String(s) => Some(s.chars().count()),
+            _ => None,
+        }
+    }
+
+    // Scalars are never empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+
+    // Like ==, but array elements may appear in any order (each element is matched
+    // to a distinct counterpart, so duplicates have to appear equally often)
+    pub fn eq_unordered(&self, other: &JsonType) -> bool {
+        match (self, other) {
+            (JsonType::Array(left), JsonType::Array(right)) => {
+                if left.len() != right.len() {
+                    return false;
+                }
+
+                let mut used = vec![false; right.len()];
+                left.iter().all(|item| {
+                    let found = right
+                        .iter()
+                        .enumerate()
+                        .position(|(i, candidate)| !used[i] && item.eq_unordered(candidate));
+
+                    match found {
+                        Some(i) => {
+                            used[i] = true;
+                            true
+                        }
+                        None => false,
+                    }
+                })
+            }
+            (JsonType::Object(left), JsonType::Object(right)) => {
+                left.len() == right.len()
+                    && left
+                        .iter()
+                        .all(|(key, value)| right.get(key).is_some_and(|other| value.eq_unordered(other)))
+            }
+            _ => self == other,
+        }
+    }
+}
+
+// Panics when the key is missing or self is not an object, use get for fallible access
+impl Index<&str> for JsonType {
+    type Output = JsonType;
+
+    fn index(&self, key: &str) -> &JsonType {
+        match self {
+            JsonType::Object(map) => map.get(key).unwrap_or_else(|| panic!("no key {key:?} in JSON object")),
+            _ => panic!("cannot index into a non-object JSON value with {key:?}"),
+        }
+    }
+}
+
+// Panics when out of bounds or self is not an array, use get_index for fallible access
+impl Index<usize> for JsonType {
+    type Output = JsonType;
+
+    fn index(&self, index: usize) -> &JsonType {
+        match self {
+            JsonType::Array(arr) => arr
+                .get(index)
+                .unwrap_or_else(|| panic!("index {index} out of bounds for JSON array of length {}", arr.len())),
+            _ => panic!("cannot index into a non-array JSON value with {index}"),
+        }
+    }
+}
+
+impl From<i64> for JsonType {
+    fn from(value: i64) -> JsonType {
+        JsonType::Number(value)
+    }
+}
+
+impl From<i32> for JsonType {
+    fn from(value: i32) -> JsonType {
+        JsonType::Number(value as i64)
+    }
+}
+
+impl From<u32> for JsonType {
+    fn from(value: u32) -> JsonType {
+        JsonType::Number(value as i64)
+    }
+}
+
+// Values above i64::MAX don't fit a Number and become a Decimal, which loses
+// precision past 2^53
+impl From<u64> for JsonType {
+    fn from(value: u64) -> JsonType {
+        match i64::try_from(value) {
+            Ok(value) => JsonType::Number(value),
+            Err(_) => JsonType::Decimal(value as f64),
+        }
+    }
+}
+
+impl From<f64> for JsonType {
+    fn from(value: f64) -> JsonType {
+        JsonType::Decimal(value)
+    }
+}
+
+// Goes through the shortest decimal form of the f32, so 0.1f32 stays 0.1 instead of
+// widening to 0.10000000149011612
+impl From<f32> for JsonType {
+    fn from(value: f32) -> JsonType {
+        JsonType::Decimal(value.to_string().parse().unwrap_or(value as f64))
+    }
+}
+
+fn split_pointer(pointer: &str) -> Result<Vec<String>, ParserError> {
+    if pointer.is_empty() {
+        return Ok(vec![]);
+    }
+
+    if !pointer.starts_with('/') {
+        return Err(ParserError::InvalidSyntax(format!("JSON pointer must start with '/': {}", pointer)));
+    }
+
+    // "~1" has to be decoded before "~0" so that "~01" becomes "~1"
+    Ok(pointer[1..]
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+fn parse_index(token: &str) -> Option<usize> {
+    // leading zeros are not allowed in array indices
+    if token.is_empty() || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+
+    token.parse::<usize>().ok()
+}
+
+fn resolve_mut<'a>(value: &'a mut JsonType, tokens: &[String]) -> Option<&'a mut JsonType> {
+    let mut current = value;
+
+    for token in tokens.iter() {
+        current = match current {
+            JsonType::Object(map) => map.get_mut(token)?,
+            JsonType::Array(arr) => arr.get_mut(parse_index(token)?)?,
+            _ => return None,
+        };
+    }
+
+    Some(current)
+}
+
+// Knobs for the parser, the defaults match parse_json: strict JSON with an object
+// or array at the root
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseOptions {
+    // how deeply objects and arrays may nest before the document is rejected
+    pub max_depth: usize,
+    // skip // line and /* block */ comments wherever whitespace is allowed
+    pub allow_comments: bool,
+    // accept a ',' right before the closing '}' or ']'
+    pub allow_trailing_commas: bool,
+    // fail on a key that appears twice in one object instead of keeping the last value
+    pub reject_duplicate_keys: bool,
+    // accept a string, number, boolean or null as the whole document
+    pub allow_scalar_root: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            max_depth: 128,
+            allow_comments: false,
+            allow_trailing_commas: false,
+            reject_duplicate_keys: false,
+            allow_scalar_root: false,
+        }
+    }
+}
+
+// Per-document parser state threaded through the recursive descent
+struct ParseContext<'o> {
+    options: &'o ParseOptions,
+    keep_values: bool,
+    depth: usize,
+    // set when collecting errors: each one with the length of the input left at its position
+    errors: Option<Vec<(usize, ParserError)>>,
+    // the rest of the document couldn't be resynchronized, no further errors are recorded
+    gave_up: bool,
+}
+
+impl<'o> ParseContext<'o> {
+    fn new(options: &'o ParseOptions, keep_values: bool) -> Self {
+        ParseContext { options, keep_values, depth: 0, errors: None, gave_up: false }
+    }
+
+    fn enter(&mut self) -> Result<(), ParserError> {
+        if self.depth >= self.options.max_depth {
+            return Err(ParserError::NotSupported(format!("Nesting deeper than {} levels", self.options.max_depth)));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    // When collecting errors, records error at the position of at and skips to the next
+    // ',' or closer of the current container so parsing goes on. Otherwise, or when the
+    // document ends before such a point, the error is returned and parsing stops.
+    fn recover<'a>(&mut self, error: ParserError, at: &'a str, closer: char) -> Result<&'a str, ParserError> {
+        let errors = match self.errors.as_mut() {
+            Some(errors) if !self.gave_up => errors,
+            _ => return Err(error),
+        };
+
+        errors.push((at.len(), error.clone()));
+        match resync(at, closer) {
+            Some(rest) => Ok(rest),
+            None => {
+                self.gave_up = true;
+                Err(error)
+            }
+        }
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    // Skips whitespace, and comments when they are enabled
+    fn skip<'a>(&self, mut input: &'a str) -> Result<&'a str, ParserError> {
+        loop {
+            input = input.trim_start();
+
+            if !self.options.allow_comments {
+                return Ok(input);
+            }
+
+            if let Some(rest) = input.strip_prefix("//") {
+                input = rest.find('\n').map_or("", |end| &rest[end + 1..]);
+            } else if let Some(rest) = input.strip_prefix("/*") {
+                match rest.find("*/") {
+                    Some(end) => input = &rest[end + 2..],
+                    None => return Err(ParserError::MissingToken("Missing '*/' for comment".to_string())),
+                }
+            } else {
+                return Ok(input);
+            }
+        }
+    }
+}
+
+pub fn parse_json(input: &str) -> Result<JsonType, ParserError>  {
+    parse_document(input, &ParseOptions::default(), true)
+}
+
+pub fn parse_json_with(input: &str, options: &ParseOptions) -> Result<JsonType, ParserError> {
+    parse_document(input, options, true)
+}
+
+// Holds the options so many documents (NDJSON lines, stream frames) can be parsed
+// without passing them around every time
+#[derive(Debug, Clone, Default)]
+pub struct JsonParser {
+    options: ParseOptions,
+}
+
+impl JsonParser {
+    pub fn new(options: ParseOptions) -> JsonParser {
+        JsonParser { options }
+    }
+
+    pub fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    pub fn parse(&self, input: &str) -> Result<JsonType, ParserError> {
+        parse_document(input, &self.options, true)
+    }
+
+    pub fn validate(&self, input: &str) -> Result<(), ParserError> {
+        parse_document(input, &self.options, false).map(|_| ())
+    }
+
+    pub fn collect_errors(&self, input: &str) -> Vec<JsonError> {
+        collect_json_errors(input, &self.options)
+    }
+}
+
+pub fn parse_json_bytes(input: &[u8]) -> Result<JsonType, ParserError> {
+    match std::str::from_utf8(input) {
+        Ok(text) => parse_json(text),
+        Err(e) => Err(ParserError::InvalidSyntax(format!("Invalid UTF-8 at byte {}", e.valid_up_to())))
+    }
+}
+
+// Entry point for fuzzers and untrusted input. Invalid UTF-8 and malformed documents are
+// errors, the parser has no panicking paths (random_inputs_never_panic keeps it that way)
+pub fn try_parse_json(input: &[u8]) -> Result<JsonType, ParserError> {
+    parse_json_bytes(input)
+}
+
+// Checks that the input is well-formed without building the tree, the
+// containers are never filled so no HashMap or Vec allocations happen
+pub fn validate_json(input: &str) -> Result<(), ParserError> {
+    parse_document(input, &ParseOptions::default(), false).map(|_| ())
+}
+
+fn parse_document(input: &str, options: &ParseOptions, keep_values: bool) -> Result<JsonType, ParserError>  {
+    parse_root(input, &mut ParseContext::new(options, keep_values))
+}
+
+// A syntax error reported by collect_json_errors, line and column count from 1 and
+// columns in characters
+#[derive(Debug, PartialEq, Clone)]
+pub struct JsonError {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+    pub error: ParserError,
+}
+
+// Reports every syntax error in the document instead of stopping at the first one, for
+// editors and linters. After an error the parser skips to the next ',' or closing bracket
+// of the enclosing object or array and carries on. An empty result means the document is
+// valid.
+pub fn collect_json_errors(input: &str, options: &ParseOptions) -> Vec<JsonError> {
+    let mut ctx = ParseContext::new(options, false);
+    ctx.errors = Some(Vec::new());
+
+    let result = parse_root(input, &mut ctx);
+    let mut errors = ctx.errors.take().unwrap_or_default();
+    if let Err(e) = result && !ctx.gave_up {
+        // failed outside any object or array, e.g. an empty document
+        let rest = input.trim_start_matches(|c: char| c == '\u{FEFF}' || c.is_whitespace());
+        errors.push((rest.len(), e));
+    }
+
+    errors
+        .into_iter()
+        .map(|(rest, error)| {
+            let offset = input.len() - rest;
+            let before = &input[..offset];
+            JsonError {
+                offset,
+                line: before.matches('\n').count() + 1,
+                column: before.rsplit('\n').next().unwrap_or("").chars().count() + 1,
+                error,
+            }
+        })
+        .collect()
+}
+
+fn parse_root(mut input: &str, ctx: &mut ParseContext) -> Result<JsonType, ParserError> {
+    // editors on Windows like to prefix documents with a byte-order mark
+    input = input.strip_prefix('\u{FEFF}').unwrap_or(input);
+    input = ctx.skip(input)?;
+
+    let Some(first) = input.chars().next() else {
+        return Err(ParserError::EmptyInput);
+    };
+
+    match first {
         '{' => {
             // Parse JSON object
-            match parse_object(&input) {
+            match parse_object(input, ctx) {
                 Ok(obj) => Ok(JsonType::Object(obj.0)),
                 Err(e) => Err(e)
             }
         },  
         '[' => {
             // Parse JSON array
-            match parse_array(&input) {
+            match parse_array(input, ctx) {
                 Ok(arr) => Ok(JsonType::Array(arr.0)),
                 Err(e) => Err(e)
             }
         },
-        _ => return Err(ParserError::UnexpectedToken(format!("Unexpected token: {}", input.chars().nth(0).unwrap())))
+        _ if ctx.options.allow_scalar_root => {
+            let (value, rest) = parse_scalar(input)?;
+            match ctx.skip(rest)?.chars().next() {
+                Some(c) => Err(ParserError::UnexpectedToken(format!("Unexpected content after document: {}", c))),
+                None => Ok(value)
+            }
+        },
+        c => Err(unexpected_token("In document", &["{", "["], c))
     }
 }
 
-fn parse_boolean(input: &str) -> Result<(bool, &str), ParserError> {
-    match input.chars().nth(0) {
-        Some('t') => {
-            if input.len() < 4 {
-                return Err(ParserError::InvalidSyntax(format!("Invalid boolean: {}", input)));
-            }
+// Builds an UnexpectedToken error that names what would have been valid at this
+// position, e.g. "In object: expected one of ',', '}' but found ']'"
+fn unexpected_token(context: &str, expected: &[&str], found: char) -> ParserError {
+    let expected = expected.iter().map(|token| format!("'{}'", token)).collect::<Vec<_>>().join(", ");
+    ParserError::UnexpectedToken(format!("{}: expected one of {} but found '{}'", context, expected, found))
+}
 
-            match &input[..4] {
-                "true" => return Ok((true, &input[4..])),
-                _ => return Err(ParserError::InvalidSyntax(format!("Invalid boolean: {}", input)))
-            };
-        },
-        Some('f') => {
-            if input.len() < 5 {
-                return Err(ParserError::InvalidSyntax(format!("Invalid boolean: {}", input)));
-            }
+const VALUE_START: [&str; 7] = ["{", "[", "\"", "true", "false", "null", "number"];
 
-            match &input[..5] {
-                "false" => return Ok((false, &input[5..])),
-                _ => return Err(ParserError::InvalidSyntax(format!("Invalid boolean: {}", input)))
-            };
-        },
-        _ => return Err(ParserError::UnexpectedToken(format!("Expected boolean, found: {}", input.chars().nth(0).unwrap_or(' '))))
+fn parse_scalar(input: &str) -> Result<(JsonType, &str), ParserError> {
+    match input.chars().next() {
+        Some('"') => parse_string(input).map(|(s, rest)| (JsonType::String(s), rest)),
+        Some('t') | Some('f') => parse_boolean(input).map(|(b, rest)| (JsonType::Boolean(b), rest)),
+        Some('n') => parse_null(input).map(|rest| (JsonType::Null, rest)),
+        Some(c) if c.is_ascii_digit() || c == '-' => parse_number(input),
+        Some(c) => Err(unexpected_token("In document", &VALUE_START, c)),
+        None => Err(ParserError::EmptyInput)
+    }
+}
+
+fn parse_null(input: &str) -> Result<&str, ParserError> {
+    match input.strip_prefix("null") {
+        Some(rest) => Ok(rest),
+        None => Err(ParserError::InvalidSyntax(format!("Invalid null: {}", input)))
+    }
+}
+
+fn parse_boolean(input: &str) -> Result<(bool, &str), ParserError> {
+    // strip_prefix rather than slicing at a fixed byte offset, which panics when the
+    // offset falls inside a multi-byte character as in "t\u{e9}"
+    if let Some(rest) = input.strip_prefix("true") {
+        return Ok((true, rest));
+    }
+    if let Some(rest) = input.strip_prefix("false") {
+        return Ok((false, rest));
+    }
+
+    match input.chars().next() {
+        Some('t') | Some('f') => Err(ParserError::InvalidSyntax(format!("Invalid boolean: {}", input))),
+        c => Err(ParserError::UnexpectedToken(format!("Expected boolean, found: {}", c.unwrap_or(' '))))
     }
 }
 
@@ -82,14 +591,71 @@ fn parse_string(input: &str) -> Result<(String, &str), ParserError> {
         return Err(ParserError::InvalidSyntax(format!("String must start with a quote: {}", input)));
     }
 
-    let end_quote_pos = input[1..].find('"');
-    match end_quote_pos {
-        Some(pos) =>{
-            let value = &input[1..pos+1].to_string();
-            Ok((value.to_string(), &input[pos+2..]))
-        } 
-        None => Err(ParserError::MissingToken("Missing closing quote for string".to_string()))
+    let mut value = String::new();
+    let mut chars = input.char_indices().skip(1);
+
+    while let Some((pos, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, &input[pos + 1..])),
+            '\\' => {
+                let escaped = match chars.next() {
+                    Some((_, escaped)) => escaped,
+                    None => break,
+                };
+
+                match escaped {
+                    '"' => value.push('"'),
+                    '\\' => value.push('\\'),
+                    '/' => value.push('/'),
+                    'b' => value.push('\u{08}'),
+                    'f' => value.push('\u{0C}'),
+                    'n' => value.push('\n'),
+                    'r' => value.push('\r'),
+                    't' => value.push('\t'),
+                    'u' => value.push(parse_unicode_escape(&mut chars)?),
+                    other => return Err(ParserError::InvalidSyntax(format!("Invalid escape sequence: \\{}", other)))
+                }
+            },
+            c => value.push(c)
+        }
+    }
+
+    Err(ParserError::MissingToken("Missing closing quote for string".to_string()))
+}
+
+// Decodes the hex digits after \u, a high surrogate must be followed by an escaped low surrogate
+fn parse_unicode_escape(chars: &mut impl Iterator<Item = (usize, char)>) -> Result<char, ParserError> {
+    let high = parse_hex4(chars)?;
+
+    if !(0xD800..0xDC00).contains(&high) {
+        return char::from_u32(high)
+            .ok_or_else(|| ParserError::InvalidSyntax(format!("Lone surrogate in unicode escape: \\u{:04x}", high)));
+    }
+
+    let low = match (chars.next(), chars.next()) {
+        (Some((_, '\\')), Some((_, 'u'))) => parse_hex4(chars)?,
+        _ => return Err(ParserError::InvalidSyntax(format!("Lone surrogate in unicode escape: \\u{:04x}", high))),
+    };
+
+    if !(0xDC00..0xE000).contains(&low) {
+        return Err(ParserError::InvalidSyntax(format!("Invalid low surrogate in unicode escape: \\u{:04x}", low)));
     }
+
+    let code_point = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+    char::from_u32(code_point)
+        .ok_or_else(|| ParserError::InvalidSyntax(format!("Invalid unicode escape: {:x}", code_point)))
+}
+
+fn parse_hex4(chars: &mut impl Iterator<Item = (usize, char)>) -> Result<u32, ParserError> {
+    let mut code = 0;
+    for _ in 0..4 {
+        let digit = chars
+            .next()
+            .and_then(|(_, c)| c.to_digit(16))
+            .ok_or_else(|| ParserError::InvalidSyntax("Expected four hex digits in unicode escape".to_string()))?;
+        code = code * 16 + digit;
+    }
+    Ok(code)
 }
 
 fn parse_number(input: &str) -> Result<(JsonType, &str), ParserError> {
@@ -100,203 +666,569 @@ fn parse_number(input: &str) -> Result<(JsonType, &str), ParserError> {
     let mut builder = String::new();
     let mut buffer = input;
 
-    loop {
-        match buffer.chars().nth(0) {
-            Some(c) => {
-                match c {
-                    '0'..='9' | '-' | '.'=> {
-                        builder.push(c);
-                        buffer = &buffer[1..];
-                    },
-                    _ =>  {
-                        if builder.is_empty() {
-                            return Err(ParserError::InvalidSyntax(format!("Invalid number: {}", input)));
-                        } 
-                        
-                        if builder.contains('.') {
-                            match builder.parse::<f64>() {
-                                Ok(num) => return Ok((JsonType::Decimal(num), buffer)),
-                                Err(_) => return Err(ParserError::InvalidSyntax(format!("Invalid number: {}", builder)))
-                            }
-                        }
-                        else {
-                            match builder.parse::<i64>() {
-                                Ok(num) => return Ok((JsonType::Number(num), buffer)),
-                                Err(_) => return Err(ParserError::InvalidSyntax(format!("Invalid number: {}", builder)))
-                            }
-                        }
-                    }
-                }
-            }
-            None => return Err(ParserError::EmptyInput),    
+    // the number ends at the first character that can't be part of it, or the end of input
+    while let Some(c) = buffer.chars().next() {
+        match c {
+            '0'..='9' | '-' | '.'=> {
+                builder.push(c);
+                buffer = &buffer[1..];
+            },
+            _ => break
         }
     }
+
+    if builder.is_empty() {
+        return Err(ParserError::InvalidSyntax(format!("Invalid number: {}", input)));
+    } 
+    
+    let number = if builder.contains('.') {
+        builder.parse::<f64>().map(JsonType::Decimal).ok()
+    } else {
+        builder.parse::<i64>().map(JsonType::Number).ok()
+    };
+
+    match number {
+        Some(number) => Ok((number, buffer)),
+        None => Err(ParserError::InvalidSyntax(format!("Invalid number: {}", builder)))
+    }
 }
 
-fn parse_array(mut input: &str) -> Result<(Vec<JsonType>, &str), ParserError> {
+fn parse_array<'a>(mut input: &'a str, ctx: &mut ParseContext) -> Result<(Vec<JsonType>, &'a str), ParserError> {
     let mut result = Vec::<JsonType>::new();
-    
-    if (input.chars().nth(0).unwrap()) != '[' {
+
+    if !input.starts_with('[') {
         return Err(ParserError::InvalidSyntax("Array must start with '['".to_string()));
     }
 
-    input = &input[1..].trim_start();
+    ctx.enter()?;
+    input = ctx.skip(&input[1..])?;
+
+    // Empty array, consumed like the end of a non-empty one
+    if let Some(rest) = input.strip_prefix(']') {
+        ctx.leave();
+        return Ok((result, ctx.skip(rest)?));
+    }
 
     loop {
-        // todo: skip whitespaces and so on 
-    
-        if input.starts_with(']') {
+        input = match parse_element(input, ctx, &mut result) {
+            Ok(rest) => ctx.skip(rest)?,
+            Err(e) => ctx.recover(e, input, ']')?,
+        };
+
+        if !input.starts_with([',', ']']) {
+            let error = match input.chars().next() {
+                Some(c) => unexpected_token("In array", &[",", "]"], c),
+                None => ParserError::MissingToken("Expected ',' or ']' in array".to_string()),
+            };
+            input = ctx.recover(error, input, ']')?;
+        }
+
+        if input.starts_with(',') {
+            // skip comma
+            input = ctx.skip(&input[1..])?;
+
+            if ctx.options.allow_trailing_commas && input.starts_with(']') {
+                input = ctx.skip(&input[1..])?;
+                break;
+            }
+        } else {
+            input = ctx.skip(&input[1..])?;
             break;
         }
+    }
 
-        match input.chars().nth(0).unwrap() {
-            '{' => {
-                match parse_object(&input) {
-                    Ok(obj) => {
-                        input = obj.1.trim_start();
-                        result.push(JsonType::Object(obj.0))
-                    },
-                    Err(e) => return Err(e)
-                }
+    ctx.leave();
+    Ok((result, input))
+}
+
+// Parses one array element into result and returns the input after it
+fn parse_element<'a>(input: &'a str, ctx: &mut ParseContext, result: &mut Vec<JsonType>) -> Result<&'a str, ParserError> {
+    let (value, rest) = match input.chars().next() {
+        Some('{') => {
+            let (obj, rest) = parse_object(input, ctx)?;
+            (JsonType::Object(obj), rest)
+        },
+        Some('[') => {
+            return Err(ParserError::NotSupported("Nested arrays not supported yet".to_string()));
+        },
+        Some('"') => {
+            let (s, rest) = parse_string(input)?;
+            (JsonType::String(s), rest)
+        },
+        Some('t') | Some('f') => {
+            let (b, rest) = parse_boolean(input)?;
+            (JsonType::Boolean(b), rest)
+        },
+        Some('n') => (JsonType::Null, parse_null(input)?),
+        Some('0'..='9') => parse_number(input)?,
+        Some(c) => return Err(unexpected_token("In array", &["{", "\"", "true", "false", "null", "number"], c)),
+        None => return Err(ParserError::MissingToken("Expected value in array".to_string()))
+    };
+
+    if ctx.keep_values {
+        result.push(value);
+    }
+    Ok(rest)
+}
+
+fn parse_object<'a>(mut input: &'a str, ctx: &mut ParseContext) -> Result<(HashMap<String, JsonType>, &'a str), ParserError> {
+    let mut result = HashMap::new();
+    let mut seen_keys = HashSet::new();
+
+    if !input.starts_with('{') {
+        return Err(ParserError::InvalidSyntax("Object must start with '{'".to_string()));
+    }
+
+    ctx.enter()?;
+    input = ctx.skip(&input[1..])?;
+
+    loop {
+        // Parse each key-value pair
+        match input.chars().next() {
+            Some('}') => {
+                // Empty object, leaves the remainder exactly like the end of a non-empty one
+                input = ctx.skip(&input[1..])?;
+                break;
             },
-            '[' => {
-                return Err(ParserError::NotSupported("Nested arrays not supported yet".to_string()));
+            Some(_) => {},
+            None => return Err(ParserError::MissingToken("Expected key or '}' in object".to_string()))
+        }
+
+        input = match parse_member(input, ctx, &mut result, &mut seen_keys) {
+            Ok(rest) => ctx.skip(rest)?,
+            Err((e, at)) => ctx.recover(e, at, '}')?,
+        };
+
+        // Check for comma or end of object
+        if !input.starts_with([',', '}']) {
+            let error = match input.chars().next() {
+                Some(c) => unexpected_token("In object", &[",", "}"], c),
+                None => ParserError::MissingToken("Expected ',' or '}' in object".to_string()),
+            };
+            input = ctx.recover(error, input, '}')?;
+        }
+
+        if input.starts_with(',') {
+            // Move past the comma
+            input = ctx.skip(&input[1..])?;
+
+            if ctx.options.allow_trailing_commas && input.starts_with('}') {
+                input = ctx.skip(&input[1..])?;
+                break;
             }
-            '"' => {
-                match parse_string(input) {
-                    Ok(s) => {
-                        input = s.1.trim_start();
-                        result.push(JsonType::String(s.0))
-                    },
-                    Err(e) => return Err(e)
+        } else {
+            input = ctx.skip(&input[1..])?;
+            break; // End of object
+        }
+    }
+
+    ctx.leave();
+    Ok((result, input))
+}
+
+// Parses one "key": value pair into result and returns the input after the value. A
+// failure comes with the input at the token that could not be parsed.
+fn parse_member<'a>(
+    input: &'a str,
+    ctx: &mut ParseContext,
+    result: &mut HashMap<String, JsonType>,
+    seen_keys: &mut HashSet<String>,
+) -> Result<&'a str, (ParserError, &'a str)> {
+    let (key, rest) = parse_string(input).map_err(|e| (e, input))?;
+
+    // Expect a colon, whitespace may sit between the key and the colon
+    let input = ctx.skip(rest).map_err(|e| (e, rest))?;
+    if !input.starts_with(':') {
+        return Err((ParserError::MissingToken("Expected ':' after key".to_string()), input));
+    }
+    let input = ctx.skip(&input[1..]).map_err(|e| (e, input))?;
+
+    let (value, rest) = match input.chars().next() {
+        Some('{') => parse_object(input, ctx).map(|(obj, rest)| (JsonType::Object(obj), rest)),
+        Some('[') => parse_array(input, ctx).map(|(arr, rest)| (JsonType::Array(arr), rest)),
+        Some('"') => parse_string(input).map(|(s, rest)| (JsonType::String(s), rest)),
+        Some('t') | Some('f') => parse_boolean(input).map(|(b, rest)| (JsonType::Boolean(b), rest)),
+        Some('n') => parse_null(input).map(|rest| (JsonType::Null, rest)),
+        Some(c) if c.is_ascii_digit() || c == '-' => parse_number(input),
+        Some(c) => Err(unexpected_token(&format!("In value for key '{}'", key), &VALUE_START, c)),
+        None => Err(ParserError::MissingToken(format!("Expected value for key '{}'", key)))
+    }
+    .map_err(|e| (e, input))?;
+
+    if ctx.options.reject_duplicate_keys && !seen_keys.insert(key.clone()) {
+        return Err((ParserError::InvalidSyntax(format!("Duplicate key in object: {}", key)), input));
+    }
+
+    if ctx.keep_values {
+        result.insert(key, value);
+    }
+    Ok(rest)
+}
+
+// Skips to the next ',' or the container's closing character on the current nesting
+// level, None when the input ends first. Used to carry on after a syntax error.
+fn resync(input: &str, closer: char) -> Option<&str> {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (pos, c) in input.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' if depth > 0 => depth -= 1,
+            ',' if depth == 0 => return Some(&input[pos..]),
+            c if c == closer && depth == 0 => return Some(&input[pos..]),
+            _ => {}
+        }
+    }
+    None
+}
+// Serializes a value into compact JSON text, object keys are written in sorted
+// order so the output is stable
+pub fn write_json(value: &JsonType) -> String {
+    let mut output = String::new();
+    write_value(value, &mut output);
+    output
+}
+
+// Re-serializes JSON text without insignificant whitespace. The input is parsed
+// first, so whitespace inside strings survives and invalid input is an error.
+pub fn minify(input: &str) -> Result<String, ParserError> {
+    parse_json(input).map(|value| write_json(&value))
+}
+
+// Parses JSON text and writes it back with every member and element on its own line,
+// indented by indent spaces per level
+pub fn prettify(input: &str, indent: usize) -> Result<String, ParserError> {
+    parse_json(input).map(|value| write_json_pretty(&value, indent))
+}
+
+// Like write_json, but one member or element per line. Empty objects and arrays
+// stay on one line as {} and [].
+pub fn write_json_pretty(value: &JsonType, indent: usize) -> String {
+    let mut output = String::new();
+    write_value_pretty(value, indent, 0, &mut output);
+    output
+}
+
+fn write_value_pretty(value: &JsonType, indent: usize, depth: usize, output: &mut String) {
+    let padding = |depth: usize| " ".repeat(indent * depth);
+
+    match value {
+        JsonType::Object(map) if !map.is_empty() => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+
+            output.push_str("{\n");
+            for (index, key) in keys.iter().enumerate() {
+                if index > 0 {
+                    output.push_str(",\n");
+                }
+                output.push_str(&padding(depth + 1));
+                write_string(key, output);
+                output.push_str(": ");
+                write_value_pretty(&map[*key], indent, depth + 1, output);
+            }
+            output.push('\n');
+            output.push_str(&padding(depth));
+            output.push('}');
+        },
+        JsonType::Array(arr) if !arr.is_empty() => {
+            output.push_str("[\n");
+            for (index, item) in arr.iter().enumerate() {
+                if index > 0 {
+                    output.push_str(",\n");
+                }
+                output.push_str(&padding(depth + 1));
+                write_value_pretty(item, indent, depth + 1, output);
+            }
+            output.push('\n');
+            output.push_str(&padding(depth));
+            output.push(']');
+        },
+        _ => write_value(value, output)
+    }
+}
+
+fn write_value(value: &JsonType, output: &mut String) {
+    match value {
+        JsonType::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+
+            output.push('{');
+            for (index, key) in keys.iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
                 }
+                write_string(key, output);
+                output.push(':');
+                write_value(&map[*key], output);
+            }
+            output.push('}');
+        },
+        JsonType::Array(arr) => {
+            output.push('[');
+            for (index, item) in arr.iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+                write_value(item, output);
+            }
+            output.push(']');
+        },
+        JsonType::String(s) => write_string(s, output),
+        JsonType::Number(n) => output.push_str(&n.to_string()),
+        JsonType::Decimal(d) => output.push_str(&format_decimal(*d)),
+        JsonType::Boolean(b) => output.push_str(if *b { "true" } else { "false" }),
+        JsonType::Null => output.push_str("null")
+    }
+}
+
+fn format_decimal(value: f64) -> String {
+    // JSON has no representation for NaN or infinity
+    if !value.is_finite() {
+        return "null".to_string();
+    }
+
+    // keep the fraction so the value reads back as a Decimal and not a Number, large
+    // whole numbers would otherwise not even fit the i64 a Number is read into
+    if value.fract() == 0.0 {
+        return format!("{:.1}", value);
+    }
+
+    value.to_string()
+}
+
+fn write_string(value: &str, output: &mut String) {
+    output.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            '\u{08}' => output.push_str("\\b"),
+            '\u{0C}' => output.push_str("\\f"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c)
+        }
+    }
+    output.push('"');
+}
+
+// Applies a JSON Patch (RFC 6902) document, an array of operations, to doc
+pub fn apply_patch(mut doc: JsonType, patch: &JsonType) -> Result<JsonType, ParserError> {
+    let operations = match patch {
+        JsonType::Array(operations) => operations,
+        _ => return Err(ParserError::InvalidSyntax("JSON patch must be an array of operations".to_string())),
+    };
+
+    for operation in operations.iter() {
+        let operation = match operation {
+            JsonType::Object(operation) => operation,
+            _ => return Err(ParserError::InvalidSyntax("JSON patch operation must be an object".to_string())),
+        };
+
+        let op = patch_member_str(operation, "op")?;
+        let path = patch_member_str(operation, "path")?;
+
+        match op {
+            "add" => patch_add(&mut doc, path, patch_member_value(operation, "value")?.clone())?,
+            "remove" => {
+                patch_remove(&mut doc, path)?;
             },
-            't' | 'f' => {
-                match parse_boolean(input) {
-                    Ok(b) => {
-                        input = b.1.trim_start();
-                        result.push(JsonType::Boolean(b.0))
-                    },
-                    Err(e) => return Err(e)
+            "replace" => {
+                let value = patch_member_value(operation, "value")?.clone();
+                match doc.pointer_mut(path) {
+                    Some(target) => *target = value,
+                    None => return Err(ParserError::PatchFailed(format!("Path not found: {}", path)))
                 }
             },
-            '0'..='9' => {
-                match parse_number(input) {
-                    Ok(n) => {
-                        input = n.1.trim_start();
-                        result.push(n.0)
-                    },
-                    Err(e) => return Err(e)
+            "move" => {
+                let from = patch_member_str(operation, "from")?;
+                if path.starts_with(from) && path[from.len()..].starts_with('/') {
+                    return Err(ParserError::PatchFailed(format!("Cannot move {} into one of its children", from)));
                 }
+                let value = patch_remove(&mut doc, from)?;
+                patch_add(&mut doc, path, value)?;
             },
-            _ => return Err(ParserError::UnexpectedToken(format!("Unexpected token in array: {}", input.chars().nth(0).unwrap())))
-        }
-
-        if input.chars().nth(0).unwrap() == ',' {
-            // skip comma
-            input = &input[1..].trim_start();
-        }
-        else if input.chars().nth(0).unwrap() == ']' {
-            input = &input[1..].trim_start();
-            break;
-        }
-        else {
-            return Err(ParserError::UnexpectedToken(format!("Expected ',' or ']' in array, found: {}", input.chars().nth(0).unwrap())));
+            "copy" => {
+                let from = patch_member_str(operation, "from")?;
+                let value = match doc.pointer(from) {
+                    Some(value) => value.clone(),
+                    None => return Err(ParserError::PatchFailed(format!("Path not found: {}", from)))
+                };
+                patch_add(&mut doc, path, value)?;
+            },
+            "test" => {
+                let expected = patch_member_value(operation, "value")?;
+                match doc.pointer(path) {
+                    Some(actual) if actual == expected => {},
+                    Some(_) => return Err(ParserError::PatchFailed(format!("Test failed for path: {}", path))),
+                    None => return Err(ParserError::PatchFailed(format!("Path not found: {}", path)))
+                }
+            },
+            other => return Err(ParserError::NotSupported(format!("Unknown patch operation: {}", other)))
         }
     }
 
-    Ok((result, &input))
+    Ok(doc)
 }
 
-fn parse_object(mut input: &str) -> Result<(HashMap<String, JsonType>, &str), ParserError> {
-    let mut result = HashMap::new();
-    
-    if input.chars().nth(0).unwrap() != '{' {
-        return Err(ParserError::InvalidSyntax("Object must start with '{'".to_string()));
+fn patch_member_value<'a>(operation: &'a HashMap<String, JsonType>, name: &str) -> Result<&'a JsonType, ParserError> {
+    match operation.get(name) {
+        Some(value) => Ok(value),
+        None => Err(ParserError::MissingToken(format!("Patch operation is missing '{}'", name)))
     }
-    
-    input = &input[1..].trim_start();
+}
 
-    loop {
-        // Parse each key-value pair
-        if (input.chars().nth(0).unwrap()) == '}' {
-            return Ok((result, &input[1..])); // Empty object
+fn patch_member_str<'a>(operation: &'a HashMap<String, JsonType>, name: &str) -> Result<&'a str, ParserError> {
+    match patch_member_value(operation, name)? {
+        JsonType::String(s) => Ok(s.as_str()),
+        _ => Err(ParserError::InvalidSyntax(format!("Patch operation member '{}' must be a string", name)))
+    }
+}
+
+fn patch_add(doc: &mut JsonType, path: &str, value: JsonType) -> Result<(), ParserError> {
+    let mut tokens = split_pointer(path)?;
+    let last = match tokens.pop() {
+        Some(last) => last,
+        None => {
+            // an empty path replaces the whole document
+            *doc = value;
+            return Ok(());
         }
+    };
+
+    match resolve_mut(doc, &tokens) {
+        Some(JsonType::Object(map)) => {
+            map.insert(last, value);
+            Ok(())
+        },
+        Some(JsonType::Array(arr)) => {
+            if last == "-" {
+                arr.push(value);
+                return Ok(());
+            }
+            match parse_index(&last) {
+                Some(index) if index <= arr.len() => {
+                    arr.insert(index, value);
+                    Ok(())
+                },
+                _ => Err(ParserError::PatchFailed(format!("Invalid array index in path: {}", path)))
+            }
+        },
+        _ => Err(ParserError::PatchFailed(format!("Path not found: {}", path)))
+    }
+}
 
-        match parse_string(&input) {
-            Ok(key) => {
-                // Expect a colon
-                input = key.1;
+fn patch_remove(doc: &mut JsonType, path: &str) -> Result<JsonType, ParserError> {
+    let mut tokens = split_pointer(path)?;
+    let last = match tokens.pop() {
+        Some(last) => last,
+        None => return Err(ParserError::PatchFailed("Cannot remove the document root".to_string())),
+    };
 
-                if input.chars().nth(0).unwrap() != ':' {
-                    return Err(ParserError::MissingToken("Expected ':' after key".to_string()));
-                }
+    let removed = match resolve_mut(doc, &tokens) {
+        Some(JsonType::Object(map)) => map.remove(&last),
+        Some(JsonType::Array(arr)) => match parse_index(&last) {
+            Some(index) if index < arr.len() => Some(arr.remove(index)),
+            _ => None
+        },
+        _ => None
+    };
 
-                input = &input[1..].trim_start();
+    match removed {
+        Some(value) => Ok(value),
+        None => Err(ParserError::PatchFailed(format!("Path not found: {}", path)))
+    }
+}
 
-                let value = if input.chars().nth(0).unwrap() == '{' {
-                    match parse_object(&input) {
-                        Ok(obj) => {
-                            input = obj.1;
-                            JsonType::Object(obj.0)
-                        },
-                        Err(e) => return Err(e)
-                    }
-                } else if input.chars().nth(0).unwrap() == '[' {
-                    match parse_array(&input) {
-                        Ok(arr) => {
-                            input = arr.1;
-                            JsonType::Array(arr.0)
-                        },
-                        Err(e) => return Err(e)
-                    }
-                } else if input.chars().nth(0).unwrap() == '"' {
-                    match parse_string(input) {
-                        Ok(s) => {
-                            input = s.1;
-                            JsonType::String(s.0)
-                        },
-                        Err(e) => return Err(e)
-                    }
-                } else if input.chars().nth(0).unwrap() == 't' || input.chars().nth(0).unwrap() == 'f' {
-                    match parse_boolean(input) {
-                        Ok(b) => {
-                            input = b.1;
-                            JsonType::Boolean(b.0)
-                        },
-                        Err(e) => return Err(e)
-                    }
-                } else if input.chars().nth(0).unwrap().is_digit(10) || input.chars().nth(0).unwrap() == '-' {
-                    match parse_number(input) {
-                        Ok(n) => {
-                            input = n.1;
-                            n.0
-                        },
-                        Err(e) => return Err(e)
-                    }
+// Walks the tree and emits every scalar leaf with its path, e.g. "address.city" or
+// "courses[0]". Object keys are visited in sorted order; empty objects and arrays
+// have no leaves and therefore emit nothing.
+pub fn flatten(value: &JsonType) -> Vec<(String, JsonType)> {
+    let mut result = Vec::new();
+    flatten_into(value, String::new(), &mut result);
+    result
+}
+
+fn flatten_into(value: &JsonType, path: String, result: &mut Vec<(String, JsonType)>) {
+    match value {
+        JsonType::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+
+            for key in keys {
+                let child_path = if path.is_empty() {
+                    key.clone()
                 } else {
-                    return Err(ParserError::UnexpectedToken(format!("Unexpected token in object value: {}", input.chars().nth(0).unwrap())));
+                    format!("{}.{}", path, key)
                 };
+                flatten_into(&map[key], child_path, result);
+            }
+        },
+        JsonType::Array(arr) => {
+            for (index, item) in arr.iter().enumerate() {
+                flatten_into(item, format!("{}[{}]", path, index), result);
+            }
+        },
+        scalar => result.push((path, scalar.clone()))
+    }
+}
+
+// Builds a new object holding only the values found at the given JSON Pointers,
+// nested under the same keys. Array indices along a pointer become object keys
+// in the projection. Pointers that don't resolve are skipped.
+pub fn project(value: &JsonType, pointers: &[&str]) -> JsonType {
+    let mut result = JsonType::Object(HashMap::new());
+
+    for pointer in pointers.iter() {
+        let selected = match value.pointer(pointer) {
+            Some(selected) => selected.clone(),
+            None => continue
+        };
+
+        let tokens = match split_pointer(pointer) {
+            Ok(tokens) => tokens,
+            Err(_) => continue
+        };
 
-                result.insert(key.0, value);
-                input = input.trim_start();
+        // the empty pointer selects the whole document
+        let (last, parents) = match tokens.split_last() {
+            Some(split) => split,
+            None => return selected
+        };
 
-                // Check for comma or end of object
-                if input.chars().nth(0).unwrap() == ',' {
-                    // Move past the comma
-                    input = &input[1..].trim_start();
-                } else if input.chars().nth(0).unwrap() == '}' {
-                    input = &input[1..].trim_start();
-                    break; // End of object
-                } else {
-                    return Err(ParserError::UnexpectedToken(format!("Expected ',' or '}}' in object, found: {}", input.chars().nth(0).unwrap())));
-                }
-            },
-            Err(e) => return Err(e)
+        let mut current = &mut result;
+        for token in parents.iter() {
+            current = match current {
+                JsonType::Object(map) => map
+                    .entry(token.clone())
+                    .or_insert_with(|| JsonType::Object(HashMap::new())),
+                _ => break
+            };
+        }
+
+        if let JsonType::Object(map) = current {
+            map.insert(last.clone(), selected);
         }
     }
 
-    Ok((result, &input))
+    result
 }
 
 #[cfg(test)]
@@ -470,9 +1402,7 @@ mod tests {
         }
     }
 
-    #[test]
-    fn read_json_object_with_real_world() {
-        let json = r#"
+    const REAL_WORLD_OBJECT: &str = r#"
         {
     "_id": "68d7cd3e0c429cb0c5dde37b",
     "index": 2,
@@ -518,6 +1448,10 @@ mod tests {
     "greeting": "Hello, Myrtle Terrell! You have 2 unread messages.",
     "favoriteFruit": "apple"
   }"#;
+
+    #[test]
+    fn read_json_object_with_real_world() {
+        let json = REAL_WORLD_OBJECT;
         let result = parse_json(json);
         match result {
             Ok(_) => {},
@@ -615,9 +1549,7 @@ mod tests {
         }
     }
 
-    #[test]
-    fn read_json_array_real_world() {
-        let json = r#"
+    const REAL_WORLD_ARRAY: &str = r#"
         [
     {
       "message": "Hello, Garnet! Your order number is: #99",
@@ -710,7 +1642,742 @@ mod tests {
   ]
         "#;
 
+    #[test]
+    fn read_json_array_real_world() {
+        let json = REAL_WORLD_ARRAY;
         let result = parse_json(json);
         assert!(result.is_ok());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn real_world_numeric_looking_strings_stay_strings() {
+        let object = parse_json(REAL_WORLD_OBJECT).unwrap();
+        assert_eq!(object["latitude"], JsonType::Decimal(56.2324));
+        assert_eq!(object["longitude"], JsonType::Decimal(-140.453));
+        assert_eq!(object["balance"], JsonType::String("$3,324.92".to_string()));
+
+        let array = parse_json(REAL_WORLD_ARRAY).unwrap();
+        let coordinates = &array[0]["location"]["coordinates"];
+        assert_eq!(coordinates["latitude"], JsonType::String("19.3044".to_string()));
+        assert_eq!(coordinates["longitude"], JsonType::String("-174.344".to_string()));
+
+        let location = &array[1]["location"];
+        assert_eq!(location["zip"], JsonType::String("67190-9477".to_string()));
+        assert_eq!(location["coordinates"]["latitude"], JsonType::String("-19.3501".to_string()));
+        assert_eq!(array[0]["location"]["zip"], JsonType::String("66298".to_string()));
+    }
+
+    #[test]
+    fn validate_json_valid_document() {
+        let json = r#"{"key1": true, "key2": [1, 2, {"nested": "value"}], "key3": {"subkey": 1.5}}"#;
+        assert_eq!(validate_json(json), Ok(()));
+        assert_eq!(validate_json("[]"), Ok(()));
+    }
+
+    #[test]
+    fn validate_json_invalid_document() {
+        let invalid = [
+            r#"{"key": tru}"#,
+            r#"{"key" 1}"#,
+            r#"[1, 2; 3]"#,
+            r#"{"key": "value"#,
+            "",
+            "nope",
+        ];
+
+        for json in invalid {
+            assert_eq!(validate_json(json), Err(parse_json(json).unwrap_err()));
+        }
+    }
+
+    #[test]
+    fn json_pointer_lookup() {
+        let json = parse_json(r#"{"address": {"city": "Anytown"}, "courses": ["Math", "Science"], "a/b": 1}"#).unwrap();
+
+        assert_eq!(json.pointer("/address/city"), Some(&JsonType::String("Anytown".to_string())));
+        assert_eq!(json.pointer("/courses/1"), Some(&JsonType::String("Science".to_string())));
+        assert_eq!(json.pointer("/a~1b"), Some(&JsonType::Number(1)));
+        assert_eq!(json.pointer(""), Some(&json));
+        assert_eq!(json.pointer("/courses/2"), None);
+        assert_eq!(json.pointer("/missing"), None);
+    }
+
+    #[test]
+    fn apply_patch_add() {
+        let doc = parse_json(r#"{"name": "John Doe", "courses": ["Math", "History"]}"#).unwrap();
+        let patch = parse_json(r#"[
+            {"op": "add", "path": "/age", "value": 30},
+            {"op": "add", "path": "/courses/1", "value": "Science"},
+            {"op": "add", "path": "/courses/-", "value": "Art"}
+        ]"#).unwrap();
+
+        let expected = parse_json(r#"{"name": "John Doe", "age": 30, "courses": ["Math", "Science", "History", "Art"]}"#).unwrap();
+        assert_eq!(apply_patch(doc, &patch), Ok(expected));
+    }
+
+    #[test]
+    fn apply_patch_remove() {
+        let doc = parse_json(r#"{"name": "John Doe", "courses": ["Math", "Science"]}"#).unwrap();
+        let patch = parse_json(r#"[
+            {"op": "remove", "path": "/name"},
+            {"op": "remove", "path": "/courses/0"}
+        ]"#).unwrap();
+
+        let expected = parse_json(r#"{"courses": ["Science"]}"#).unwrap();
+        assert_eq!(apply_patch(doc, &patch), Ok(expected));
+    }
+
+    #[test]
+    fn apply_patch_replace_move_copy() {
+        let doc = parse_json(r#"{"name": "John Doe", "address": {"city": "Anytown"}}"#).unwrap();
+        let patch = parse_json(r#"[
+            {"op": "replace", "path": "/name", "value": "Jane Doe"},
+            {"op": "copy", "from": "/address/city", "path": "/birthplace"},
+            {"op": "move", "from": "/address/city", "path": "/city"}
+        ]"#).unwrap();
+
+        let expected = parse_json(r#"{"name": "Jane Doe", "address": {}, "birthplace": "Anytown", "city": "Anytown"}"#).unwrap();
+        assert_eq!(apply_patch(doc, &patch), Ok(expected));
+    }
+
+    #[test]
+    fn apply_patch_failing_test_operation() {
+        let doc = parse_json(r#"{"name": "John Doe"}"#).unwrap();
+        let patch = parse_json(r#"[
+            {"op": "test", "path": "/name", "value": "Jane Doe"},
+            {"op": "remove", "path": "/name"}
+        ]"#).unwrap();
+
+        assert_eq!(
+            apply_patch(doc, &patch),
+            Err(ParserError::PatchFailed("Test failed for path: /name".to_string()))
+        );
+    }
+
+    #[test]
+    fn apply_patch_missing_path() {
+        let doc = parse_json(r#"{"name": "John Doe"}"#).unwrap();
+        let patch = parse_json(r#"[{"op": "remove", "path": "/age"}]"#).unwrap();
+
+        assert_eq!(
+            apply_patch(doc, &patch),
+            Err(ParserError::PatchFailed("Path not found: /age".to_string()))
+        );
+    }
+
+    #[test]
+    fn flatten_person() {
+        let json = parse_json(r#"
+        {
+            "name": "John Doe",
+            "age": 30,
+            "is_student": false,
+            "courses": ["Math", "Science", "History"],
+            "address": {
+                "street": "123 Main St",
+                "city": "Anytown",
+                "zip": "12345"
+            },
+            "tags": {}
+        }
+        "#).unwrap();
+
+        let flat = flatten(&json);
+
+        assert_eq!(flat.len(), 9);
+        assert!(flat.contains(&("name".to_string(), JsonType::String("John Doe".to_string()))));
+        assert!(flat.contains(&("age".to_string(), JsonType::Number(30))));
+        assert!(flat.contains(&("address.city".to_string(), JsonType::String("Anytown".to_string()))));
+        assert!(flat.contains(&("courses[0]".to_string(), JsonType::String("Math".to_string()))));
+        assert!(flat.contains(&("courses[2]".to_string(), JsonType::String("History".to_string()))));
+        assert!(!flat.iter().any(|(path, _)| path.starts_with("tags")));
+    }
+
+    #[test]
+    fn get_object_member_and_array_element() {
+        let json = parse_json(r#"
+        {
+            "name": "John Doe",
+            "courses": ["Math", "Science", "History"],
+            "address": {
+                "city": "Anytown"
+            }
+        }
+        "#).unwrap();
+
+        assert_eq!(json.get("name"), Some(&JsonType::String("John Doe".to_string())));
+        assert_eq!(
+            json.get("address").and_then(|address| address.get("city")),
+            Some(&JsonType::String("Anytown".to_string()))
+        );
+        assert_eq!(json.get("age"), None);
+
+        let courses = json.get("courses").unwrap();
+        assert_eq!(courses.get_index(1), Some(&JsonType::String("Science".to_string())));
+        assert_eq!(courses.get_index(3), None);
+
+        // the wrong kind of container gives None
+        assert_eq!(courses.get("0"), None);
+        assert_eq!(json.get_index(0), None);
+        assert_eq!(JsonType::Null.get("name"), None);
+    }
+
+    #[test]
+    fn index_operator_chain() {
+        let json = parse_json(r#"
+        {
+            "courses": ["Math", "Science"],
+            "address": {
+                "city": "Anytown"
+            }
+        }
+        "#).unwrap();
+
+        assert_eq!(json["address"]["city"], JsonType::String("Anytown".to_string()));
+        assert_eq!(json["courses"][1], JsonType::String("Science".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "no key \"zip\"")]
+    fn index_operator_missing_key_panics() {
+        let json = parse_json(r#"{"address": {"city": "Anytown"}}"#).unwrap();
+        let _ = &json["address"]["zip"];
+    }
+
+    #[test]
+    fn project_selected_pointers() {
+        let json = parse_json(r#"
+        {
+            "name": "John Doe",
+            "age": 30,
+            "courses": ["Math", "Science", "History"],
+            "address": {
+                "street": "123 Main St",
+                "city": "Anytown",
+                "zip": "12345"
+            }
+        }
+        "#).unwrap();
+
+        let projected = project(&json, &["/name", "/address/city", "/missing", "/address/country"]);
+
+        let expected = parse_json(r#"{"name": "John Doe", "address": {"city": "Anytown"}}"#).unwrap();
+        assert_eq!(projected, expected);
+    }
+
+    #[test]
+    fn read_json_object_with_byte_order_mark() {
+        let result = parse_json("\u{FEFF}{\"key\": true}");
+        match result {
+            Ok(JsonType::Object(map)) => {
+                assert_eq!(map.get("key"), Some(&JsonType::Boolean(true)));
+            },
+            _ => panic!("Expected JSON object")
+        }
+    }
+
+    #[test]
+    fn read_json_bytes_with_byte_order_mark() {
+        let result = parse_json_bytes(b"\xEF\xBB\xBF[1, 2]");
+        assert_eq!(result, Ok(JsonType::Array(vec![JsonType::Number(1), JsonType::Number(2)])));
+
+        assert!(matches!(parse_json_bytes(b"[\xFF]"), Err(ParserError::InvalidSyntax(_))));
+    }
+
+    #[test]
+    fn whitespace_between_nested_values_and_separators() {
+        let json = parse_json("{\"a\": {\"b\": 1}\n\n  ,\n \"c\": [1, {\"d\": [] }\n\t, {}\r\n ]\r\n, \"e\": {}\n}").unwrap();
+
+        assert_eq!(json["a"]["b"], JsonType::Number(1));
+        assert_eq!(json["c"][1]["d"], JsonType::Array(vec![]));
+        assert_eq!(json["c"][2], JsonType::Object(HashMap::new()));
+        assert_eq!(json["e"], JsonType::Object(HashMap::new()));
+
+        let json = parse_json("[ {\"a\": 1}\n , {}\n\n, 2 ]").unwrap();
+        assert_eq!(json[1], JsonType::Object(HashMap::new()));
+        assert_eq!(json[2], JsonType::Number(2));
+    }
+
+    const FUZZ_DOCUMENT: &str = r#"{"name": "Jé \"x\"", "n": -12.5, "arr": [1, true, null, "s\/", {"a": []}], "o": {"b": false}, "e": {}}"#;
+
+    #[test]
+    fn truncated_documents_error_cleanly() {
+        assert!(parse_json(FUZZ_DOCUMENT).is_ok());
+
+        for (end, _) in FUZZ_DOCUMENT.char_indices() {
+            let truncated = &FUZZ_DOCUMENT[..end];
+            assert!(parse_json(truncated).is_err(), "accepted truncated input {:?}", truncated);
+            assert!(validate_json(truncated).is_err(), "validated truncated input {:?}", truncated);
+        }
+    }
+
+    #[test]
+    fn multibyte_after_boolean_start_is_an_error() {
+        for input in [r#"{"a": t"é"}"#, r#"{"a": fé}"#, "[t😀]", "[tru€]", r#"{"a": tr"#] {
+            assert!(matches!(parse_json(input), Err(ParserError::InvalidSyntax(_))), "{:?}", input);
+        }
+    }
+
+    // Random token soup and single-character mutations of a valid document, from a
+    // fixed-seed generator so failures reproduce. Any panic fails the test.
+    #[test]
+    fn random_inputs_never_panic() {
+        let alphabet: Vec<char> = "{}[]\":,-0123456789.eE+ truefalsn\\u/\n\t\u{e9}\u{20ac}\u{1F600}*".chars().collect();
+        let document: Vec<char> = FUZZ_DOCUMENT.chars().collect();
+        let lenient = ParseOptions {
+            allow_comments: true,
+            allow_trailing_commas: true,
+            allow_scalar_root: true,
+            ..ParseOptions::default()
+        };
+
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = |bound: usize| {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+            (seed >> 33) as usize % bound
+        };
+
+        for _ in 0..20_000 {
+            let soup: String = (0..next(24)).map(|_| alphabet[next(alphabet.len())]).collect();
+            let mut mutated = document.clone();
+            let position = next(mutated.len());
+            mutated[position] = alphabet[next(alphabet.len())];
+            let mutated: String = mutated.into_iter().collect();
+
+            for input in [&soup, &mutated] {
+                let _ = parse_json(input);
+                let _ = parse_json_with(input, &lenient);
+                let _ = validate_json(input);
+                let _ = collect_json_errors(input, &lenient);
+            }
+        }
+    }
+
+    #[test]
+    fn try_parse_json_accepts_any_bytes() {
+        assert_eq!(try_parse_json(b"[true]"), Ok(JsonType::Array(vec![JsonType::Boolean(true)])));
+        assert!(try_parse_json(b"[t\xC3\xA9]").is_err());
+        assert!(try_parse_json(b"{\"a\": \xFF}").is_err());
+        assert_eq!(try_parse_json(b""), Err(ParserError::EmptyInput));
+        assert_eq!(try_parse_json(b"[\"\xE2\x82\"]"), Err(ParserError::InvalidSyntax("Invalid UTF-8 at byte 2".to_string())));
+        assert_eq!(try_parse_json("\u{FEFF}  ".as_bytes()), Err(ParserError::EmptyInput));
+    }
+
+    #[test]
+    fn collect_errors_reports_every_problem() {
+        let input = "{\n  \"name\": tru,\n  \"age\": 30,\n  \"tags\": [\"a\" \"b\"],\n  \"ok\": null\n}";
+
+        let errors = collect_json_errors(input, &ParseOptions::default());
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+
+        assert!(matches!(errors[0].error, ParserError::InvalidSyntax(_)));
+        assert_eq!((errors[0].line, errors[0].column), (2, 11));
+        assert_eq!(&input[errors[0].offset..errors[0].offset + 3], "tru");
+
+        assert!(matches!(errors[1].error, ParserError::UnexpectedToken(_)));
+        assert_eq!((errors[1].line, errors[1].column), (4, 16));
+
+        // parse_json still stops at the first one
+        assert_eq!(parse_json(input).unwrap_err(), errors[0].error);
+    }
+
+    #[test]
+    fn collect_errors_valid_and_truncated_documents() {
+        let parser = JsonParser::default();
+        assert_eq!(parser.collect_errors(r#"{"a": [1, 2], "b": {"c": null}}"#), vec![]);
+
+        // nothing to resynchronize on once the input ends, so the error is reported once
+        let errors = parser.collect_errors(r#"{"a": [1, 2"#);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].offset, 11);
+        assert!(matches!(errors[0].error, ParserError::MissingToken(_)));
+
+        let errors = parser.collect_errors("  ");
+        assert_eq!(errors, vec![JsonError { offset: 2, line: 1, column: 3, error: ParserError::EmptyInput }]);
+    }
+
+    #[test]
+    fn collect_errors_in_array_of_objects() {
+        let input = r#"[{"a": 1,, "b": 2}, {"c": x}, {"d": [1, }]"#;
+        let errors = collect_json_errors(input, &ParseOptions::default());
+
+        // the stray '}' is skipped while looking for the end of the inner array, which
+        // leaves the last object and the outer array unterminated
+        let offsets: Vec<usize> = errors.iter().map(|error| error.offset).collect();
+        assert_eq!(offsets, vec![9, 26, 40, input.len()]);
+    }
+
+    #[test]
+    fn parse_object_remainder() {
+        let options = ParseOptions::default();
+        let mut ctx = ParseContext::new(&options, true);
+
+        let (map, rest) = parse_object("{}, 1", &mut ctx).unwrap();
+        assert!(map.is_empty());
+        assert_eq!(rest, ", 1");
+
+        let (map, rest) = parse_object("{ }  ]", &mut ctx).unwrap();
+        assert!(map.is_empty());
+        assert_eq!(rest, "]");
+
+        let (map, rest) = parse_object(r#"{"a":1 } ]"#, &mut ctx).unwrap();
+        assert_eq!(map.get("a"), Some(&JsonType::Number(1)));
+        assert_eq!(rest, "]");
+    }
+
+    #[test]
+    fn parse_object_unterminated() {
+        assert_eq!(
+            parse_json("{"),
+            Err(ParserError::MissingToken("Expected key or '}' in object".to_string()))
+        );
+        assert_eq!(
+            parse_json("{  "),
+            Err(ParserError::MissingToken("Expected key or '}' in object".to_string()))
+        );
+    }
+
+    #[test]
+    fn write_json_compact() {
+        let json = parse_json(r#"{"name": "John Doe", "age": 30, "ratio": 0.5, "whole": 2.0, "active": true, "courses": ["Math", "Science"], "address": {"city": "Anytown"}}"#).unwrap();
+
+        assert_eq!(
+            write_json(&json),
+            r#"{"active":true,"address":{"city":"Anytown"},"age":30,"courses":["Math","Science"],"name":"John Doe","ratio":0.5,"whole":2.0}"#
+        );
+    }
+
+    #[test]
+    fn write_json_large_decimals_round_trip() {
+        for value in [1e20, -1e20, 1e16, 123456789012345680000.0, f64::MAX] {
+            let mut map = HashMap::new();
+            map.insert("value".to_string(), JsonType::Decimal(value));
+            let json = JsonType::Object(map);
+
+            let written = write_json(&json);
+            assert_eq!(parse_json(&written), Ok(json), "{}", written);
+        }
+        assert_eq!(format_decimal(1e20), "100000000000000000000.0");
+    }
+
+    #[test]
+    fn write_json_escapes_strings() {
+        let json = JsonType::String("say \"hi\"\\\n\u{01}".to_string());
+
+        assert_eq!(write_json(&json), r#""say \"hi\"\\\n\u0001""#);
+    }
+
+    #[test]
+    fn read_json_object_with_escaped_key() {
+        let json = parse_json(r#"{"a\"b": 1, "caf\u00e9": "\ud83d\ude00\n"}"#).unwrap();
+
+        let JsonType::Object(map) = json else { panic!("Expected an object") };
+        assert_eq!(map.get("a\"b"), Some(&JsonType::Number(1)));
+        assert_eq!(map.get("caf\u{e9}"), Some(&JsonType::String("\u{1F600}\n".to_string())));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn read_json_escaped_forward_slash() {
+        let json = parse_json(r#"{"url": "http:\/\/example.com", "a\/b": ["\/"]}"#).unwrap();
+
+        assert_eq!(json["url"], JsonType::String("http://example.com".to_string()));
+        assert_eq!(json["a/b"][0], JsonType::String("/".to_string()));
+        // slashes need no escaping, so they are written back as is
+        assert_eq!(write_json(&json["url"]), r#""http://example.com""#);
+    }
+
+    #[test]
+    fn read_json_string_invalid_escapes() {
+        assert!(matches!(parse_json(r#"{"a": "\q"}"#), Err(ParserError::InvalidSyntax(_))));
+        assert!(matches!(parse_json(r#"{"a": "\ud83d"}"#), Err(ParserError::InvalidSyntax(_))));
+        assert!(matches!(parse_json(r#"{"a": "\u12"}"#), Err(ParserError::InvalidSyntax(_))));
+    }
+
+    #[test]
+    fn json_type_predicates() {
+        let json = parse_json(r#"{"object": {}, "array": [null], "string": "a", "number": 1, "decimal": 1.5, "boolean": true, "null": null}"#).unwrap();
+
+        type Predicate = fn(&JsonType) -> bool;
+        let predicates: [(&str, Predicate); 7] = [
+            ("null", JsonType::is_null),
+            ("object", JsonType::is_object),
+            ("array", JsonType::is_array),
+            ("string", JsonType::is_string),
+            ("number", JsonType::is_number),
+            ("decimal", JsonType::is_decimal),
+            ("boolean", JsonType::is_boolean),
+        ];
+
+        for (key, _) in predicates.iter() {
+            let value = json.pointer(&format!("/{}", key)).unwrap();
+            let matching: Vec<&str> = predicates
+                .iter()
+                .filter(|(_, predicate)| predicate(value))
+                .map(|(name, _)| *name)
+                .collect();
+
+            assert_eq!(matching, vec![*key]);
+        }
+
+        assert_eq!(json.pointer("/array/0"), Some(&JsonType::Null));
+        assert_eq!(write_json(json.pointer("/array").unwrap()), "[null]");
+    }
+
+    #[test]
+    fn json_numeric_accessors() {
+        assert_eq!(JsonType::Number(3).as_f64(), Some(3.0));
+        assert_eq!(JsonType::Decimal(3.5).as_f64(), Some(3.5));
+        assert_eq!(JsonType::String("3".to_string()).as_f64(), None);
+
+        assert_eq!(JsonType::Number(-7).as_i64(), Some(-7));
+        assert_eq!(JsonType::Decimal(2.0).as_i64(), Some(2));
+        assert_eq!(JsonType::Decimal(2.5).as_i64(), None);
+        assert_eq!(JsonType::Decimal(f64::NAN).as_i64(), None);
+        assert_eq!(JsonType::Decimal(1e300).as_i64(), None);
+        assert_eq!(JsonType::Boolean(true).as_i64(), None);
+    }
+
+    #[test]
+    fn json_eq_unordered() {
+        let left = parse_json(r#"{"tags": ["a", "b", "a"], "items": [{"id": 1, "flags": [true, false]}, {"id": 2}]}"#).unwrap();
+        let right = parse_json(r#"{"items": [{"id": 2}, {"flags": [false, true], "id": 1}], "tags": ["a", "a", "b"]}"#).unwrap();
+
+        assert!(left.eq_unordered(&right));
+        assert_ne!(left, right);
+
+        let different = parse_json(r#"{"tags": ["a", "b", "b"], "items": [{"id": 1, "flags": [true, false]}, {"id": 2}]}"#).unwrap();
+        assert!(!left.eq_unordered(&different));
+
+        let shorter = parse_json(r#"["a", "b"]"#).unwrap();
+        assert!(!shorter.eq_unordered(&parse_json(r#"["a", "b", "a"]"#).unwrap()));
+    }
+
+    #[test]
+    fn json_len() {
+        let json = parse_json(r#"{"name": "Jos\u00e9", "courses": ["Math", "Science"], "address": {}, "age": 30}"#).unwrap();
+
+        assert_eq!(json.len(), Some(4));
+        assert_eq!(json.pointer("/courses").unwrap().len(), Some(2));
+        assert_eq!(json.pointer("/name").unwrap().len(), Some(4));
+        assert_eq!(json.pointer("/age").unwrap().len(), None);
+
+        assert!(json.pointer("/address").unwrap().is_empty());
+        assert!(JsonType::String(String::new()).is_empty());
+        assert!(!json.is_empty());
+        assert!(!JsonType::Number(0).is_empty());
+    }
+
+    #[test]
+    fn minify_real_world() {
+        let minified = minify(REAL_WORLD_OBJECT).unwrap();
+        assert_eq!(minified, write_json(&parse_json(REAL_WORLD_OBJECT).unwrap()));
+        assert!(minified.starts_with(r#"{"_id":"68d7cd3e0c429cb0c5dde37b","about":"Non cillum"#));
+        assert!(minified.contains(r#""friends":[{"id":0,"name":"Isabella Lawrence"},{"id":1"#));
+        assert_eq!(parse_json(&minified), parse_json(REAL_WORLD_OBJECT));
+
+        // whitespace inside strings is kept
+        assert_eq!(minify("{ \"a b\" : [ 1 , \" c \" ] }"), Ok(r#"{"a b":[1," c "]}"#.to_string()));
+        assert!(minify("{\"name\": }").is_err());
+    }
+
+    #[test]
+    fn prettify_compact_object() {
+        let compact = r#"{"name":"John Doe","age":30,"courses":["Math",{"title":"Science","credits":5}],"address":{},"tags":[]}"#;
+        let expected = r#"{
+  "address": {},
+  "age": 30,
+  "courses": [
+    "Math",
+    {
+      "credits": 5,
+      "title": "Science"
+    }
+  ],
+  "name": "John Doe",
+  "tags": []
+}"#;
+
+        assert_eq!(prettify(compact, 2), Ok(expected.to_string()));
+        assert_eq!(minify(expected), minify(compact));
+        assert_eq!(prettify("[1,2]", 4), Ok("[\n    1,\n    2\n]".to_string()));
+        assert!(prettify("{\"name\":", 2).is_err());
+    }
+
+    #[test]
+    fn json_from_numbers() {
+        assert_eq!(JsonType::from(-42i32), JsonType::Number(-42));
+        assert_eq!(JsonType::from(i32::MIN), JsonType::Number(i32::MIN as i64));
+        assert_eq!(JsonType::from(u32::MAX), JsonType::Number(4294967295));
+        assert_eq!(JsonType::from(i64::MIN), JsonType::Number(i64::MIN));
+        assert_eq!(JsonType::from(i64::MAX as u64), JsonType::Number(i64::MAX));
+        assert_eq!(JsonType::from(u64::MAX), JsonType::Decimal(18446744073709551615.0));
+        assert_eq!(JsonType::from(i64::MAX as u64 + 1), JsonType::Decimal(9223372036854775808.0));
+        assert_eq!(JsonType::from(2.5f64), JsonType::Decimal(2.5));
+        assert_eq!(JsonType::from(0.1f32), JsonType::Decimal(0.1));
+        assert_eq!(JsonType::from(-1.5f32), JsonType::Decimal(-1.5));
+    }
+
+    #[test]
+    fn json_take_and_replace() {
+        let mut json = parse_json(r#"{"person": {"name": "John Doe"}, "courses": ["Math"]}"#).unwrap();
+
+        let person = json.pointer_mut("/person").unwrap().take();
+        assert_eq!(write_json(&person), r#"{"name":"John Doe"}"#);
+        assert_eq!(json.pointer("/person"), Some(&JsonType::Null));
+
+        let courses = json.pointer_mut("/courses").unwrap();
+        let old = courses.replace(JsonType::Array(vec![JsonType::String("Science".to_string())]));
+        assert_eq!(old, JsonType::Array(vec![JsonType::String("Math".to_string())]));
+        assert_eq!(write_json(&json), r#"{"courses":["Science"],"person":null}"#);
+
+        let mut scalar = JsonType::Number(1);
+        assert_eq!(scalar.take(), JsonType::Number(1));
+        assert!(scalar.is_null());
+    }
+
+    #[test]
+    fn json_insert_remove_push() {
+        let mut json = parse_json(r#"{"name": "John Doe", "courses": ["Math"]}"#).unwrap();
+
+        assert_eq!(json.insert("age".to_string(), JsonType::Number(30)), Ok(None));
+        assert_eq!(
+            json.insert("name".to_string(), JsonType::String("Jane Doe".to_string())),
+            Ok(Some(JsonType::String("John Doe".to_string())))
+        );
+        assert_eq!(json.remove("age"), Some(JsonType::Number(30)));
+        assert_eq!(json.remove("age"), None);
+
+        let courses = json.pointer_mut("/courses").unwrap();
+        assert_eq!(courses.push(JsonType::String("Science".to_string())), Ok(()));
+        assert!(matches!(courses.insert("x".to_string(), JsonType::Null), Err(ParserError::NotSupported(_))));
+        assert_eq!(courses.remove("0"), None);
+
+        assert_eq!(write_json(&json), r#"{"courses":["Math","Science"],"name":"Jane Doe"}"#);
+        assert!(matches!(JsonType::Null.push(JsonType::Null), Err(ParserError::NotSupported(_))));
+    }
+
+    #[test]
+    fn read_json_object_with_space_before_colon() {
+        let json = parse_json(r#"{"a" : 1}"#).unwrap();
+        assert_eq!(json.pointer("/a"), Some(&JsonType::Number(1)));
+
+        let json = parse_json("{\"a\"  :  1, \"b\"\n:\t\"c\"}").unwrap();
+        assert_eq!(json.pointer("/a"), Some(&JsonType::Number(1)));
+        assert_eq!(json.pointer("/b"), Some(&JsonType::String("c".to_string())));
+
+        assert!(matches!(parse_json(r#"{"a" 1}"#), Err(ParserError::MissingToken(_))));
+    }
+
+    #[test]
+    fn read_json_object_value_dispatch() {
+        let json = parse_json(r#"{"a":   "b"}"#).unwrap();
+        assert_eq!(json.pointer("/a"), Some(&JsonType::String("b".to_string())));
+
+        assert!(matches!(parse_json(r#"{"a":}"#), Err(ParserError::UnexpectedToken(_))));
+        assert!(matches!(parse_json(r#"{"a":"#), Err(ParserError::MissingToken(_))));
+        assert!(matches!(parse_json(r#"{"a":   "#), Err(ParserError::MissingToken(_))));
+    }
+
+    #[test]
+    fn parse_json_with_options() {
+        let input = "{\n  // the user\n  \"name\": \"John Doe\", /* inline */\n  \"courses\": [\"Math\", \"Science\",],\n}";
+        assert!(parse_json(input).is_err());
+
+        let options = ParseOptions {
+            allow_comments: true,
+            allow_trailing_commas: true,
+            ..ParseOptions::default()
+        };
+        let json = parse_json_with(input, &options).unwrap();
+        assert_eq!(write_json(&json), r#"{"courses":["Math","Science"],"name":"John Doe"}"#);
+
+        let options = ParseOptions { allow_trailing_commas: false, ..options };
+        assert!(matches!(parse_json_with(input, &options), Err(ParserError::UnexpectedToken(_))));
+    }
+
+    #[test]
+    fn parse_json_with_strict_options() {
+        let options = ParseOptions {
+            max_depth: 2,
+            reject_duplicate_keys: true,
+            allow_scalar_root: true,
+            ..ParseOptions::default()
+        };
+
+        assert_eq!(parse_json_with(" 42 ", &options), Ok(JsonType::Number(42)));
+        assert_eq!(parse_json_with(r#""hi""#, &options), Ok(JsonType::String("hi".to_string())));
+        assert_eq!(parse_json_with("null", &options), Ok(JsonType::Null));
+        assert!(matches!(parse_json_with("true false", &options), Err(ParserError::UnexpectedToken(_))));
+        assert!(matches!(parse_json("42"), Err(ParserError::UnexpectedToken(_))));
+
+        assert!(matches!(parse_json_with(r#"{"a": 1, "a": 2}"#, &options), Err(ParserError::InvalidSyntax(_))));
+        assert_eq!(parse_json(r#"{"a": 1, "a": 2}"#).unwrap().pointer("/a"), Some(&JsonType::Number(2)));
+
+        assert!(parse_json_with(r#"{"a": {"b": 1}}"#, &options).is_ok());
+        assert!(matches!(parse_json_with(r#"{"a": {"b": {"c": 1}}}"#, &options), Err(ParserError::NotSupported(_))));
+    }
+
+    #[test]
+    fn json_parser_reuses_options() {
+        let parser = JsonParser::new(ParseOptions {
+            allow_comments: true,
+            allow_scalar_root: true,
+            ..ParseOptions::default()
+        });
+
+        let lines = [
+            r#"{"id": 1, "name": "John Doe"} // first"#,
+            r#"[1, 2, 3]"#,
+            r#"/* bare */ "text""#,
+            "true",
+        ];
+
+        let documents: Vec<JsonType> = lines.iter().map(|line| parser.parse(line).unwrap()).collect();
+        assert_eq!(documents[0].pointer("/name"), Some(&JsonType::String("John Doe".to_string())));
+        assert_eq!(documents[1].len(), Some(3));
+        assert_eq!(documents[2], JsonType::String("text".to_string()));
+        assert_eq!(documents[3], JsonType::Boolean(true));
+
+        assert!(parser.validate(r#"{"a": 1, /* ok */ "b": 2}"#).is_ok());
+        assert!(parser.parse("{,}").is_err());
+        assert!(JsonParser::default().parse("true").is_err());
+    }
+
+    #[test]
+    fn unexpected_token_names_expected_tokens() {
+        assert_eq!(
+            parse_json(r#"{"a": 1]"#),
+            Err(ParserError::UnexpectedToken("In object: expected one of ',', '}' but found ']'".to_string()))
+        );
+        assert_eq!(
+            parse_json(r#"["a" "b"]"#),
+            Err(ParserError::UnexpectedToken("In array: expected one of ',', ']' but found '\"'".to_string()))
+        );
+        assert_eq!(
+            parse_json(r#"{"a": x}"#),
+            Err(ParserError::UnexpectedToken(
+                "In value for key 'a': expected one of '{', '[', '\"', 'true', 'false', 'null', 'number' but found 'x'".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_json("x"),
+            Err(ParserError::UnexpectedToken("In document: expected one of '{', '[' but found 'x'".to_string()))
+        );
+    }
+
+    #[test]
+    fn read_json_empty_and_unterminated_arrays() {
+        assert_eq!(parse_json("[ ]"), Ok(JsonType::Array(vec![])));
+        assert_eq!(parse_json("[\n\t]"), Ok(JsonType::Array(vec![])));
+
+        let json = parse_json(r#"{"tags": [ ], "name": "x"}"#).unwrap();
+        assert_eq!(json.pointer("/tags"), Some(&JsonType::Array(vec![])));
+        assert_eq!(json.pointer("/name"), Some(&JsonType::String("x".to_string())));
+
+        assert!(matches!(parse_json("["), Err(ParserError::MissingToken(_))));
+        assert!(matches!(parse_json("[1,"), Err(ParserError::MissingToken(_))));
+        assert!(matches!(parse_json("[1, "), Err(ParserError::MissingToken(_))));
+        assert!(matches!(parse_json("[1,]"), Err(ParserError::UnexpectedToken(_))));
+    }
+}
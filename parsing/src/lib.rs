@@ -1,20 +1,62 @@
+mod base64;
+mod client;
+mod cookie;
 mod json;
+mod date;
+mod files;
+mod gzip;
+mod hash;
+mod html;
 mod http;
+mod query;
+mod router;
+mod stream;
+#[cfg(any(test, feature = "test-util"))]
+mod testing;
+mod urlencoding;
+mod websocket;
+mod xml;
 
-pub use json::{JsonType, ParserError, FromJson, parse_json};
+pub use json::{JsonType, ParserError, JsonError, FromJson, ParseOptions, JsonParser, parse_json, parse_json_with, parse_json_bytes, try_parse_json, write_json, write_json_pretty, minify, prettify, validate_json, collect_json_errors, apply_patch, flatten, project};
 pub use http::{
     HttpMethod,
     HttpRequest,
     HttpPath,
     HttpVersion,
     HttpResponse,
+    HttpBody,
     HttpPlatform,
+    HttpPlatformMetrics,
+    HttpServer,
     HttpContentType,
+    CacheControl,
     HttpStatusCode,
     KnownHeader,
     HttpRequestError,
+    HttpHandler,
+    HttpLogger,
+    only,
     read_http_request,
+    read_http_request_with_limit,
+    read_http_request_partial,
+    read_http_request_buf,
+    read_http_request_streaming,
     write_http_request,
     write_http_response,
     read_http_response,
-};
\ No newline at end of file
+};
+pub use client::HttpClient;
+#[cfg(any(test, feature = "test-util"))]
+pub use testing::{start_test_platform, start_test_server, TestServer};
+pub use cookie::Cookie;
+pub use query::{FromQuery, QueryValue};
+pub use base64::{base64_encode, base64_decode};
+pub use date::HttpDate;
+pub use files::serve_file;
+pub use hash::{sha1, sha256};
+pub use html::html_escape;
+pub use stream::JsonArrayWriter;
+pub use router::{assign_request_id, Middleware, Next, Router};
+pub use urlencoding::{percent_encode, percent_decode, encode_query, decode_query};
+pub use websocket::{websocket_accept_key, websocket_handshake};
+pub use xml::{XmlNode, XmlParserError, parse_xml};
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use crate::http::HttpRequestError;
+use crate::urlencoding::percent_decode;
+
+// Builds a value from the query parameters of a request, the counterpart of FromJson.
+// Implementations are usually generated with from_query!.
+pub trait FromQuery: Sized {
+    fn from_query(query: &HashMap<String, String>) -> Result<Self, HttpRequestError>;
+}
+
+// A single field of a FromQuery struct. Plain types are required, Option<T> fields
+// are None when the parameter is missing.
+pub trait QueryValue: Sized {
+    fn from_query_value(name: &str, value: Option<&str>) -> Result<Self, HttpRequestError>;
+}
+
+impl<T: QueryValue> QueryValue for Option<T> {
+    fn from_query_value(name: &str, value: Option<&str>) -> Result<Self, HttpRequestError> {
+        match value {
+            Some(value) => T::from_query_value(name, Some(value)).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+macro_rules! impl_query_value {
+    ($($ty:ty),*) => {
+        $(
+            impl QueryValue for $ty {
+                fn from_query_value(name: &str, value: Option<&str>) -> Result<Self, HttpRequestError> {
+                    let value = value.ok_or_else(|| {
+                        HttpRequestError::InvalidRequest(format!("Missing query parameter: {}", name))
+                    })?;
+
+                    percent_decode(value)?.parse().map_err(|_| {
+                        HttpRequestError::InvalidRequest(format!("Invalid value for query parameter {}: {}", name, value))
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_query_value!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64, bool, char, String);
+
+// Declares a struct and implements FromQuery for it, each field is read from the
+// query parameter of the same name:
+//
+//     from_query! {
+//         pub struct Pagination {
+//             page: u32,
+//             size: Option<u32>,
+//         }
+//     }
+#[macro_export]
+macro_rules! from_query {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($field_vis:vis $field:ident : $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($field_vis $field: $ty),*
+        }
+
+        impl $crate::FromQuery for $name {
+            fn from_query(
+                query: &::std::collections::HashMap<::std::string::String, ::std::string::String>,
+            ) -> ::std::result::Result<Self, $crate::HttpRequestError> {
+                ::std::result::Result::Ok($name {
+                    $(
+                        $field: <$ty as $crate::QueryValue>::from_query_value(
+                            stringify!($field),
+                            query.get(stringify!($field)).map(|value| value.as_str()),
+                        )?,
+                    )*
+                })
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::http::read_http_request;
+
+    crate::from_query! {
+        #[derive(Debug, PartialEq)]
+        struct Pagination {
+            page: u32,
+            size: Option<u32>,
+        }
+    }
+
+    crate::from_query! {
+        #[derive(Debug, PartialEq)]
+        struct Search {
+            q: String,
+            exact: Option<bool>,
+        }
+    }
+
+    fn get(path: &str) -> crate::http::HttpRequest {
+        let request_str = format!("GET {} HTTP/1.1\r\nHost: example.com\r\n\r\n", path);
+        read_http_request(request_str.as_str()).unwrap()
+    }
+
+    #[test]
+    fn from_query_struct() {
+        let request = get("/people?page=3&size=20");
+        assert_eq!(request.query::<Pagination>(), Ok(Pagination { page: 3, size: Some(20) }));
+
+        let request = get("/people?page=3");
+        assert_eq!(request.query::<Pagination>(), Ok(Pagination { page: 3, size: None }));
+
+        let request = get("/search?q=caf%C3%A9%20au%20lait&exact=true");
+        assert_eq!(
+            request.query::<Search>(),
+            Ok(Search {
+                q: "caf\u{e9} au lait".to_string(),
+                exact: Some(true)
+            })
+        );
+    }
+
+    #[test]
+    fn from_query_errors() {
+        assert!(get("/people?size=20").query::<Pagination>().is_err());
+        assert!(get("/people").query::<Pagination>().is_err());
+        assert!(get("/people?page=three").query::<Pagination>().is_err());
+        assert!(get("/people?page=1&size=-1").query::<Pagination>().is_err());
+    }
+}
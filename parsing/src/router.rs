@@ -0,0 +1,630 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::http::{HttpContentType, HttpHandler, HttpMethod, HttpPlatform, HttpRequest, HttpResponse, HttpStatusCode, HttpVersion, KnownHeader};
+
+// Middleware runs around every request the router handles, it can change the request,
+// call the rest of the chain through next.run(request) and change the response
+pub type Middleware = fn(HttpRequest, Next) -> HttpResponse;
+
+pub struct Next<'a> {
+    middleware: &'a [Middleware],
+    router: &'a Router,
+}
+
+impl Next<'_> {
+    pub fn run(self, request: HttpRequest) -> HttpResponse {
+        match self.middleware.split_first() {
+            Some((middleware, rest)) => middleware(
+                request,
+                Next {
+                    middleware: rest,
+                    router: self.router,
+                },
+            ),
+            None => self.router.dispatch_request(request),
+        }
+    }
+}
+
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+// Keeps the X-Request-Id the client sent or assigns the next id of this process,
+// the id is stored on the request and echoed in the response
+pub fn assign_request_id(mut request: HttpRequest, next: Next) -> HttpResponse {
+    let id = match request.header("X-Request-Id") {
+        Some(KnownHeader::RequestId(id)) if !id.is_empty() => id.clone(),
+        _ => format!("{:016x}", NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed)),
+    };
+    request.request_id = Some(id.clone());
+
+    let mut response = next.run(request);
+    response.headers.insert("X-Request-Id".to_string(), KnownHeader::RequestId(id));
+    response
+}
+
+#[derive(Clone)]
+struct Route {
+    host: Option<String>,
+    method: HttpMethod,
+    path: String,
+    handler: HttpHandler,
+    // Content-Type a request body has to have, set with Router::accepts
+    accepts: Option<HttpContentType>,
+    // Content-Type of the responses, checked against Accept, set with Router::produces
+    produces: Option<HttpContentType>,
+}
+
+#[derive(Clone, Default)]
+pub struct Router {
+    routes: Vec<Route>,
+    fallback: Option<HttpHandler>,
+    middleware: Vec<Middleware>,
+}
+
+impl Router {
+    pub fn new() -> Router {
+        Router {
+            routes: Vec::new(),
+            fallback: None,
+            middleware: Vec::new(),
+        }
+    }
+
+    pub fn add_route(&mut self, method: HttpMethod, path: &str, handler: HttpHandler) {
+        self.routes.push(Route {
+            host: None,
+            method,
+            path: path.to_string(),
+            handler,
+            accepts: None,
+            produces: None,
+        });
+    }
+
+    // Routes scoped to a host only answer requests whose Host header (without port) matches
+    pub fn add_host_route(&mut self, host: &str, method: HttpMethod, path: &str, handler: HttpHandler) {
+        self.routes.push(Route {
+            host: Some(host.to_lowercase()),
+            method,
+            path: path.to_string(),
+            handler,
+            accepts: None,
+            produces: None,
+        });
+    }
+
+    pub fn route(mut self, method: HttpMethod, path: &str, handler: HttpHandler) -> Router {
+        self.add_route(method, path, handler);
+        self
+    }
+
+    pub fn host_route(mut self, host: &str, method: HttpMethod, path: &str, handler: HttpHandler) -> Router {
+        self.add_host_route(host, method, path, handler);
+        self
+    }
+
+    // Restricts the route added last to request bodies of the given Content-Type,
+    // anything else is answered with 415 before the handler runs
+    pub fn accepts(mut self, content_type: HttpContentType) -> Router {
+        if let Some(route) = self.routes.last_mut() {
+            route.accepts = Some(content_type);
+        }
+        self
+    }
+
+    // Declares the Content-Type the route added last responds with, requests whose
+    // Accept header rules it out are answered with 406 before the handler runs
+    pub fn produces(mut self, content_type: HttpContentType) -> Router {
+        if let Some(route) = self.routes.last_mut() {
+            route.produces = Some(content_type);
+        }
+        self
+    }
+
+    // Called when no route matches the path, without one a plain 404 is returned
+    pub fn fallback(mut self, handler: HttpHandler) -> Router {
+        self.fallback = Some(handler);
+        self
+    }
+
+    // Middleware is run in registration order, the first one registered is the outermost
+    pub fn middleware(mut self, middleware: Middleware) -> Router {
+        self.middleware.push(middleware);
+        self
+    }
+
+    pub fn into_platform(self) -> HttpPlatform {
+        HttpPlatform::new(move |request| self.handle(request))
+    }
+
+    pub fn handle(&self, request: HttpRequest) -> HttpResponse {
+        Next {
+            middleware: &self.middleware,
+            router: self,
+        }
+        .run(request)
+    }
+
+    fn dispatch_request(&self, request: HttpRequest) -> HttpResponse {
+        let host = request
+            .header("Host")
+            .and_then(|header| header.host_and_port())
+            .map(|(host, _)| host.to_lowercase());
+
+        // host-scoped routes win, anything they don't know about falls back to the default routes
+        let scoped: Vec<&Route> = self
+            .routes
+            .iter()
+            .filter(|route| route.host.is_some() && route.host == host)
+            .collect();
+
+        if scoped.iter().any(|route| route.matches(&request.path.normalized_path())) {
+            return dispatch(&scoped, self.fallback, request);
+        }
+
+        let default: Vec<&Route> = self.routes.iter().filter(|route| route.host.is_none()).collect();
+        dispatch(&default, self.fallback, request)
+    }
+}
+
+impl Route {
+    // A pattern ending in "/*" matches anything below that prefix, a '*' anywhere
+    // else in the pattern is taken literally
+    fn wildcard_prefix(&self) -> Option<&str> {
+        self.path
+            .strip_suffix('*')
+            .filter(|prefix| prefix.ends_with('/'))
+    }
+
+    // Requests without a body pass, one with a body needs the declared Content-Type
+    fn accepts_body_of(&self, request: &HttpRequest) -> bool {
+        let accepted = match &self.accepts {
+            Some(accepted) => accepted,
+            None => return true,
+        };
+
+        match request.header("Content-Type") {
+            Some(KnownHeader::ContentType(content_type)) => content_type == accepted,
+            _ => request.body.as_deref().is_none_or(str::is_empty),
+        }
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        self.path == path || self.wildcard_prefix().is_some_and(|prefix| path.starts_with(prefix))
+    }
+}
+
+fn dispatch(routes: &[&Route], fallback: Option<HttpHandler>, mut request: HttpRequest) -> HttpResponse {
+    let path = request.path.normalized_path();
+
+    // an exact route always wins over a wildcard, and a longer wildcard over a shorter one
+    let mut matching: Vec<&&Route> = routes.iter().filter(|route| route.path == path).collect();
+
+    if matching.is_empty() {
+        let longest_prefix = routes
+            .iter()
+            .filter_map(|route| route.wildcard_prefix())
+            .filter(|prefix| path.starts_with(prefix))
+            .map(|prefix| prefix.len())
+            .max();
+
+        if let Some(prefix_len) = longest_prefix {
+            matching = routes
+                .iter()
+                .filter(|route| route.wildcard_prefix().is_some_and(|prefix| prefix.len() == prefix_len && path.starts_with(prefix)))
+                .collect();
+            request.wildcard = Some(path[prefix_len..].to_string());
+        }
+    }
+
+    if matching.is_empty() {
+        return match fallback {
+            Some(fallback) => fallback(request),
+            None => status_response(request.version, HttpStatusCode::NotFound),
+        };
+    }
+
+    match matching.iter().find(|route| route.method == request.method) {
+        Some(route) if !route.accepts_body_of(&request) => {
+            status_response(request.version, HttpStatusCode::UnsupportedMediaType)
+        }
+        Some(route) if route.produces.as_ref().is_some_and(|produced| !request.accepts(produced)) => {
+            status_response(request.version, HttpStatusCode::NotAcceptable)
+        }
+        Some(route) => (route.handler)(request),
+        None => method_not_allowed(request.version, &matching),
+    }
+}
+
+// The Allow header lists the methods registered for the path, in registration order
+fn method_not_allowed(version: HttpVersion, routes: &[&&Route]) -> HttpResponse {
+    let mut methods: Vec<&str> = Vec::new();
+    for route in routes {
+        let method = route.method.as_str();
+        if !methods.contains(&method) {
+            methods.push(method);
+        }
+    }
+
+    let mut response = status_response(version, HttpStatusCode::MethodNotAllowed);
+    response.headers.insert("Allow".to_string(), KnownHeader::Allow(methods.join(", ")));
+    response
+}
+
+fn status_response(version: HttpVersion, status_code: HttpStatusCode) -> HttpResponse {
+    HttpResponse {
+        version,
+        status_code,
+        headers: HashMap::new(),
+        cookies: Vec::new(),
+        body: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{read_http_request, HttpBody};
+
+    fn text_response(body: &str) -> HttpResponse {
+        let mut headers = HashMap::new();
+        headers.insert("Content-Length".to_string(), KnownHeader::ContentLength(body.len()));
+
+        HttpResponse {
+            version: HttpVersion::HTTP11,
+            status_code: HttpStatusCode::OK,
+            headers,
+            cookies: Vec::new(),
+            body: Some(HttpBody::Text(body.to_string())),
+        }
+    }
+
+    fn api_index(_req: HttpRequest) -> HttpResponse {
+        text_response("api")
+    }
+
+    fn www_index(_req: HttpRequest) -> HttpResponse {
+        text_response("www")
+    }
+
+    fn default_index(_req: HttpRequest) -> HttpResponse {
+        text_response("default")
+    }
+
+    fn host_router() -> Router {
+        let mut router = Router::new();
+        router.add_host_route("api.example.com", HttpMethod::GET, "/", api_index);
+        router.add_host_route("www.example.com", HttpMethod::GET, "/", www_index);
+        router.add_route(HttpMethod::GET, "/", default_index);
+        router
+    }
+
+    fn get(host: &str, path: &str) -> HttpRequest {
+        let request_str = format!("GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", path, host);
+        read_http_request(request_str.as_str()).unwrap()
+    }
+
+    #[test]
+    fn route_by_host() {
+        let router = host_router();
+
+        let response = router.handle(get("api.example.com", "/"));
+        assert_eq!(response.body, Some(HttpBody::Text("api".to_string())));
+
+        let response = router.handle(get("www.example.com:8080", "/"));
+        assert_eq!(response.body, Some(HttpBody::Text("www".to_string())));
+    }
+
+    #[test]
+    fn route_unknown_host_uses_default_routes() {
+        let router = host_router();
+
+        let response = router.handle(get("other.example.com", "/"));
+        assert_eq!(response.body, Some(HttpBody::Text("default".to_string())));
+    }
+
+    #[test]
+    fn route_host_falls_back_for_unknown_path() {
+        let mut router = host_router();
+        router.add_route(HttpMethod::GET, "/health", default_index);
+
+        let response = router.handle(get("api.example.com", "/health"));
+        assert_eq!(response.body, Some(HttpBody::Text("default".to_string())));
+    }
+
+    #[test]
+    fn route_not_found() {
+        let router = host_router();
+
+        let response = router.handle(get("api.example.com", "/missing"));
+        assert_eq!(response.status_code, HttpStatusCode::NotFound);
+    }
+
+    #[test]
+    fn route_method_not_allowed() {
+        let router = host_router();
+
+        let request = read_http_request("POST / HTTP/1.1\r\nHost: api.example.com\r\n\r\n").unwrap();
+        let response = router.handle(request);
+        assert_eq!(response.status_code, HttpStatusCode::MethodNotAllowed);
+        assert_eq!(response.headers.get("Allow"), Some(&KnownHeader::Allow("GET".to_string())));
+    }
+
+    #[test]
+    fn route_method_not_allowed_lists_methods() {
+        let router = Router::new()
+            .route(HttpMethod::GET, "/x", default_index)
+            .route(HttpMethod::PUT, "/x", submit)
+            .route(HttpMethod::DELETE, "/y", submit);
+
+        let request = read_http_request("POST /x HTTP/1.1\r\nHost: example.com\r\n\r\n").unwrap();
+        let response = router.handle(request);
+        assert_eq!(response.status_code, HttpStatusCode::MethodNotAllowed);
+        assert_eq!(response.headers.get("Allow"), Some(&KnownHeader::Allow("GET, PUT".to_string())));
+    }
+
+    fn submit(req: HttpRequest) -> HttpResponse {
+        text_response(req.body.unwrap_or_default().as_str())
+    }
+
+    #[test]
+    fn router_into_platform() {
+        let platform = Router::new()
+            .route(HttpMethod::GET, "/", default_index)
+            .route(HttpMethod::POST, "/submit", submit)
+            .into_platform();
+
+        let response = (platform.app)(get("example.com", "/"));
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+        assert_eq!(response.body, Some(HttpBody::Text("default".to_string())));
+
+        let request = read_http_request("POST /submit HTTP/1.1\r\nHost: example.com\r\n\r\nsubmitted").unwrap();
+        let response = (platform.app)(request);
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+        assert_eq!(response.body, Some(HttpBody::Text("submitted".to_string())));
+
+        let response = (platform.app)(get("example.com", "/missing"));
+        assert_eq!(response.status_code, HttpStatusCode::NotFound);
+    }
+
+    fn not_found_page(req: HttpRequest) -> HttpResponse {
+        let mut response = text_response(&format!("Nothing at {}", req.path.normalized_path()));
+        response.status_code = HttpStatusCode::NotFound;
+        response
+    }
+
+    #[test]
+    fn route_fallback_for_unknown_path() {
+        let router = Router::new()
+            .route(HttpMethod::GET, "/", default_index)
+            .fallback(not_found_page);
+
+        let response = router.handle(get("example.com", "/missing"));
+        assert_eq!(response.status_code, HttpStatusCode::NotFound);
+        assert_eq!(response.body, Some(HttpBody::Text("Nothing at /missing".to_string())));
+
+        let response = router.handle(get("example.com", "/"));
+        assert_eq!(response.body, Some(HttpBody::Text("default".to_string())));
+
+        // a known path with another method is still answered with 405
+        let request = read_http_request("POST / HTTP/1.1\r\nHost: example.com\r\n\r\n").unwrap();
+        let response = router.handle(request);
+        assert_eq!(response.status_code, HttpStatusCode::MethodNotAllowed);
+    }
+
+    fn static_file(req: HttpRequest) -> HttpResponse {
+        text_response(req.wildcard.unwrap_or_default().as_str())
+    }
+
+    fn favicon(_req: HttpRequest) -> HttpResponse {
+        text_response("favicon")
+    }
+
+    #[test]
+    fn route_wildcard_captures_remainder() {
+        let router = Router::new().route(HttpMethod::GET, "/static/*", static_file);
+
+        let response = router.handle(get("example.com", "/static/css/app.css"));
+        assert_eq!(response.body, Some(HttpBody::Text("css/app.css".to_string())));
+
+        let response = router.handle(get("example.com", "/other/app.css"));
+        assert_eq!(response.status_code, HttpStatusCode::NotFound);
+    }
+
+    #[test]
+    fn route_specific_path_wins_over_wildcard() {
+        let router = Router::new()
+            .route(HttpMethod::GET, "/static/*", static_file)
+            .route(HttpMethod::GET, "/static/favicon.ico", favicon)
+            .route(HttpMethod::GET, "/static/css/*", static_file);
+
+        let response = router.handle(get("example.com", "/static/favicon.ico"));
+        assert_eq!(response.body, Some(HttpBody::Text("favicon".to_string())));
+
+        let response = router.handle(get("example.com", "/static/css/app.css"));
+        assert_eq!(response.body, Some(HttpBody::Text("app.css".to_string())));
+
+        let response = router.handle(get("example.com", "/static/js/app.js"));
+        assert_eq!(response.body, Some(HttpBody::Text("js/app.js".to_string())));
+    }
+
+    #[test]
+    fn route_wildcard_only_at_end() {
+        let router = Router::new().route(HttpMethod::GET, "/*/app.css", static_file);
+
+        let response = router.handle(get("example.com", "/static/app.css"));
+        assert_eq!(response.status_code, HttpStatusCode::NotFound);
+    }
+
+    #[test]
+    fn route_matches_normalized_path() {
+        let router = Router::new()
+            .route(HttpMethod::GET, "/a/b", favicon)
+            .route(HttpMethod::GET, "/static/*", static_file);
+
+        let response = router.handle(get("example.com", "/a//b"));
+        assert_eq!(response.body, Some(HttpBody::Text("favicon".to_string())));
+
+        let response = router.handle(get("example.com", "/a/./c/../b"));
+        assert_eq!(response.body, Some(HttpBody::Text("favicon".to_string())));
+
+        let response = router.handle(get("example.com", "/static/../../static/css//app.css"));
+        assert_eq!(response.body, Some(HttpBody::Text("css/app.css".to_string())));
+    }
+
+    fn add_header(request: HttpRequest, next: Next) -> HttpResponse {
+        let mut response = next.run(request);
+        response.headers.insert("X-Powered-By".to_string(), KnownHeader::Other("parsing".to_string()));
+        response
+    }
+
+    fn push_tag(mut response: HttpResponse, tag: &str) -> HttpResponse {
+        let value = match response.headers.remove("X-Chain") {
+            Some(KnownHeader::Other(value)) => format!("{}, {}", value, tag),
+            _ => tag.to_string(),
+        };
+        response.headers.insert("X-Chain".to_string(), KnownHeader::Other(value));
+        response
+    }
+
+    fn tag_outer(request: HttpRequest, next: Next) -> HttpResponse {
+        push_tag(next.run(request), "outer")
+    }
+
+    fn tag_inner(request: HttpRequest, next: Next) -> HttpResponse {
+        push_tag(next.run(request), "inner")
+    }
+
+    fn require_auth(request: HttpRequest, next: Next) -> HttpResponse {
+        if request.header("Authorization").is_none() {
+            return status_response(request.version, HttpStatusCode::Unauthorized);
+        }
+        next.run(request)
+    }
+
+    #[test]
+    fn middleware_adds_header() {
+        let router = Router::new()
+            .route(HttpMethod::GET, "/", default_index)
+            .middleware(add_header);
+
+        let response = router.handle(get("example.com", "/"));
+        assert_eq!(response.body, Some(HttpBody::Text("default".to_string())));
+        assert_eq!(
+            response.headers.get("X-Powered-By"),
+            Some(&KnownHeader::Other("parsing".to_string()))
+        );
+
+        // middleware also wraps responses the router makes itself
+        let response = router.handle(get("example.com", "/missing"));
+        assert_eq!(response.status_code, HttpStatusCode::NotFound);
+        assert!(response.headers.contains_key("X-Powered-By"));
+    }
+
+    #[test]
+    fn middleware_runs_in_registration_order() {
+        let router = Router::new()
+            .route(HttpMethod::GET, "/", default_index)
+            .middleware(tag_outer)
+            .middleware(tag_inner);
+
+        // the innermost middleware sees the response first
+        let response = router.handle(get("example.com", "/"));
+        assert_eq!(response.headers.get("X-Chain"), Some(&KnownHeader::Other("inner, outer".to_string())));
+    }
+
+    #[test]
+    fn middleware_can_short_circuit() {
+        let router = Router::new()
+            .route(HttpMethod::GET, "/", default_index)
+            .middleware(require_auth);
+
+        let response = router.handle(get("example.com", "/"));
+        assert_eq!(response.status_code, HttpStatusCode::Unauthorized);
+        assert_eq!(response.body, None);
+
+        let request = read_http_request("GET / HTTP/1.1\r\nHost: example.com\r\nAuthorization: Basic YTpi\r\n\r\n").unwrap();
+        let response = router.handle(request);
+        assert_eq!(response.body, Some(HttpBody::Text("default".to_string())));
+    }
+
+    fn echo_request_id(req: HttpRequest) -> HttpResponse {
+        text_response(req.request_id.unwrap_or_default().as_str())
+    }
+
+    #[test]
+    fn request_id_generated_and_preserved() {
+        let router = Router::new()
+            .route(HttpMethod::GET, "/", echo_request_id)
+            .middleware(assign_request_id);
+
+        let first = router.handle(get("example.com", "/"));
+        let second = router.handle(get("example.com", "/"));
+
+        let id = match first.headers.get("X-Request-Id") {
+            Some(KnownHeader::RequestId(id)) => id.clone(),
+            other => panic!("Expected X-Request-Id, got {:?}", other),
+        };
+        assert!(!id.is_empty());
+        assert_eq!(first.body, Some(HttpBody::Text(id.clone())));
+        assert_ne!(second.headers.get("X-Request-Id"), Some(&KnownHeader::RequestId(id)));
+
+        let request = read_http_request("GET / HTTP/1.1\r\nHost: example.com\r\nX-Request-Id: abc-123\r\n\r\n").unwrap();
+        let response = router.handle(request);
+        assert_eq!(response.headers.get("X-Request-Id"), Some(&KnownHeader::RequestId("abc-123".to_string())));
+        assert_eq!(response.body, Some(HttpBody::Text("abc-123".to_string())));
+    }
+
+    #[test]
+    fn route_accepts_content_type() {
+        let router = Router::new()
+            .route(HttpMethod::POST, "/people", submit)
+            .accepts(HttpContentType::ApplicationJson)
+            .route(HttpMethod::POST, "/notes", submit);
+
+        let request = read_http_request(
+            "POST /people HTTP/1.1\r\nHost: example.com\r\nContent-Type: application/xml\r\n\r\n<name>John</name>",
+        )
+        .unwrap();
+        let response = router.handle(request);
+        assert_eq!(response.status_code, HttpStatusCode::UnsupportedMediaType);
+
+        let request = read_http_request(
+            "POST /people HTTP/1.1\r\nHost: example.com\r\nContent-Type: application/json; charset=utf-8\r\n\r\n{\"name\": \"John\"}",
+        )
+        .unwrap();
+        let response = router.handle(request);
+        assert_eq!(response.body, Some(HttpBody::Text("{\"name\": \"John\"}".to_string())));
+
+        // a body without any Content-Type is refused as well
+        let request = read_http_request("POST /people HTTP/1.1\r\nHost: example.com\r\n\r\nname=John").unwrap();
+        assert_eq!(router.handle(request).status_code, HttpStatusCode::UnsupportedMediaType);
+
+        // other routes are not restricted
+        let request = read_http_request(
+            "POST /notes HTTP/1.1\r\nHost: example.com\r\nContent-Type: application/xml\r\n\r\n<note/>",
+        )
+        .unwrap();
+        assert_eq!(router.handle(request).status_code, HttpStatusCode::OK);
+    }
+
+    #[test]
+    fn route_produces_checks_accept() {
+        let router = Router::new()
+            .route(HttpMethod::GET, "/people", default_index)
+            .produces(HttpContentType::ApplicationJson);
+
+        let request = read_http_request("GET /people HTTP/1.1\r\nHost: example.com\r\nAccept: text/html\r\n\r\n").unwrap();
+        let response = router.handle(request);
+        assert_eq!(response.status_code, HttpStatusCode::NotAcceptable);
+
+        let request =
+            read_http_request("GET /people HTTP/1.1\r\nHost: example.com\r\nAccept: text/html, application/*;q=0.5\r\n\r\n")
+                .unwrap();
+        assert_eq!(router.handle(request).status_code, HttpStatusCode::OK);
+
+        // no Accept header means anything is fine
+        assert_eq!(router.handle(get("example.com", "/people")).status_code, HttpStatusCode::OK);
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::http::{HttpContentType, HttpResponse, HttpStatusCode, HttpVersion, KnownHeader};
+use crate::json::{write_json, JsonType};
+
+// Writes a JSON array response one element at a time with chunked transfer encoding,
+// so large collections never have to be serialized into a single string
+pub struct JsonArrayWriter<W: Write> {
+    writer: W,
+    first: bool,
+}
+
+impl<W: Write> JsonArrayWriter<W> {
+    // Writes the response head and the opening bracket
+    pub fn new(mut writer: W, version: HttpVersion, status_code: HttpStatusCode) -> io::Result<JsonArrayWriter<W>> {
+        let mut headers = HashMap::new();
+        headers.insert("Content-Type".to_string(), KnownHeader::ContentType(HttpContentType::ApplicationJson));
+        headers.insert("Transfer-Encoding".to_string(), KnownHeader::TransferEncoding("chunked".to_string()));
+
+        let head = HttpResponse {
+            version,
+            status_code,
+            headers,
+            cookies: Vec::new(),
+            body: None,
+        };
+        writer.write_all(&head.to_bytes())?;
+        write_chunk(&mut writer, b"[")?;
+
+        Ok(JsonArrayWriter { writer, first: true })
+    }
+
+    pub fn write(&mut self, value: &JsonType) -> io::Result<()> {
+        let mut element = String::new();
+        if !self.first {
+            element.push(',');
+        }
+        element.push_str(&write_json(value));
+        self.first = false;
+
+        write_chunk(&mut self.writer, element.as_bytes())
+    }
+
+    // Closes the array and ends the chunked body, the writer is handed back
+    pub fn finish(mut self) -> io::Result<W> {
+        write_chunk(&mut self.writer, b"]")?;
+        self.writer.write_all(b"0\r\n\r\n")?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+fn write_chunk<W: Write>(writer: &mut W, data: &[u8]) -> io::Result<()> {
+    write!(writer, "{:X}\r\n", data.len())?;
+    writer.write_all(data)?;
+    writer.write_all(b"\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Joins the chunks of a chunked body, the head has to be stripped already
+    fn dechunk(mut body: &str) -> String {
+        let mut output = String::new();
+        loop {
+            let (size, rest) = body.split_once("\r\n").unwrap();
+            let size = usize::from_str_radix(size, 16).unwrap();
+            if size == 0 {
+                assert_eq!(rest, "\r\n");
+                return output;
+            }
+            output.push_str(&rest[..size]);
+            assert_eq!(&rest[size..size + 2], "\r\n");
+            body = &rest[size + 2..];
+        }
+    }
+
+    fn person(name: &str, age: i64) -> JsonType {
+        let mut map = HashMap::new();
+        map.insert("name".to_string(), JsonType::String(name.to_string()));
+        map.insert("age".to_string(), JsonType::Number(age));
+        JsonType::Object(map)
+    }
+
+    #[test]
+    fn json_array_writer_matches_full_array() {
+        let people = vec![person("John Doe", 30), person("Jane Doe", 28), JsonType::Null];
+
+        let mut writer = JsonArrayWriter::new(Vec::new(), HttpVersion::HTTP11, HttpStatusCode::OK).unwrap();
+        for value in &people {
+            writer.write(value).unwrap();
+        }
+        let output = String::from_utf8(writer.finish().unwrap()).unwrap();
+
+        let (head, body) = output.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(head.contains("Transfer-Encoding: chunked"));
+        assert!(head.contains("Content-Type: application/json"));
+        assert_eq!(dechunk(body), write_json(&JsonType::Array(people)));
+    }
+
+    #[test]
+    fn json_array_writer_empty() {
+        let writer = JsonArrayWriter::new(Vec::new(), HttpVersion::HTTP11, HttpStatusCode::OK).unwrap();
+        let output = String::from_utf8(writer.finish().unwrap()).unwrap();
+
+        let (_, body) = output.split_once("\r\n\r\n").unwrap();
+        assert_eq!(dechunk(body), "[]");
+    }
+}
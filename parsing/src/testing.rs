@@ -0,0 +1,104 @@
+// Test support: runs a platform on an ephemeral port for tests that talk to a real socket.
+// Other crates get it through the "test-util" feature.
+use std::{
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::http::{HttpPlatform, HttpRequest, HttpResponse};
+
+pub struct TestServer {
+    addr: SocketAddr,
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+// Starts a platform with default settings around handler
+pub fn start_test_server<F>(handler: F) -> TestServer
+where
+    F: Fn(HttpRequest) -> HttpResponse + Send + Sync + 'static,
+{
+    start_test_platform(HttpPlatform::new(handler))
+}
+
+// Serves platform on 127.0.0.1 with a port picked by the OS, one thread per connection
+pub fn start_test_platform(platform: HttpPlatform) -> TestServer {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let stopped = Arc::new(AtomicBool::new(false));
+
+    let thread = thread::spawn({
+        let stopped = stopped.clone();
+        move || {
+            for stream in listener.incoming() {
+                if stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else { continue };
+                let platform = platform.clone();
+                thread::spawn(move || platform.handle_request(stream));
+            }
+        }
+    });
+
+    TestServer {
+        addr,
+        stopped,
+        thread: Some(thread),
+    }
+}
+
+impl TestServer {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    // Stops accepting connections and waits for the accept loop to end. Connections that
+    // are already open are served to completion by their own threads.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        let Some(thread) = self.thread.take() else { return };
+        self.stopped.store(true, Ordering::SeqCst);
+        // wakes the accept loop up so it sees the flag
+        let _ = TcpStream::connect(self.addr);
+        let _ = thread.join();
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::HttpClient;
+    use crate::http::{HttpBody, HttpStatusCode};
+
+    #[test]
+    fn serve_fetch_and_shut_down() {
+        let server = start_test_server(|request| HttpResponse::text(HttpStatusCode::OK, &request.path.path));
+        assert_ne!(server.addr().port(), 0);
+
+        let response = HttpClient::new().get(&server.url("/hello")).unwrap();
+        assert_eq!(response.status_code, HttpStatusCode::OK);
+        assert_eq!(response.body, Some(HttpBody::Text("/hello".to_string())));
+
+        let addr = server.addr();
+        server.shutdown();
+        assert!(TcpStream::connect(addr).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use crate::http::HttpRequestError;
+
+// Unreserved characters (RFC 3986 2.3) are the only ones left as they are
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~')
+}
+
+pub fn percent_encode(input: &str) -> String {
+    let mut output = String::new();
+    for byte in input.bytes() {
+        if is_unreserved(byte) {
+            output.push(byte as char);
+        } else {
+            output.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    output
+}
+
+// Decodes %XX sequences, the decoded bytes have to form valid UTF-8
+pub fn percent_decode(input: &str) -> Result<String, HttpRequestError> {
+    let bytes = input.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'%' {
+            output.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+
+        let byte = input
+            .get(i + 1..i + 3)
+            .filter(|hex| hex.bytes().all(|c| c.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| HttpRequestError::InvalidRequest(format!("Invalid percent-encoding in: {}", input)))?;
+
+        output.push(byte);
+        i += 3;
+    }
+
+    String::from_utf8(output)
+        .map_err(|_| HttpRequestError::InvalidRequest(format!("Percent-encoded value is not valid UTF-8: {}", input)))
+}
+
+// Builds a query string with keys sorted so the same parameters always give the same URL,
+// values keep their order and a key without values is written on its own
+pub fn encode_query(params: &HashMap<String, Vec<String>>) -> String {
+    let mut keys: Vec<&String> = params.keys().collect();
+    keys.sort();
+
+    let mut pairs = Vec::new();
+    for key in keys {
+        let values = &params[key];
+        if values.is_empty() {
+            pairs.push(percent_encode(key));
+        }
+        for value in values {
+            pairs.push(format!("{}={}", percent_encode(key), percent_encode(value)));
+        }
+    }
+
+    pairs.join("&")
+}
+
+// Parses an application/x-www-form-urlencoded string ("a=1&b=x+y&a=2") into values per
+// key in the order they appear. '+' stands for a space, a key without '=' gets an empty
+// value and empty pairs from "&&" are skipped.
+pub fn decode_query(input: &str) -> Result<HashMap<String, Vec<String>>, HttpRequestError> {
+    let mut params: HashMap<String, Vec<String>> = HashMap::new();
+
+    for pair in input.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let key = percent_decode(&key.replace('+', " "))?;
+        let value = percent_decode(&value.replace('+', " "))?;
+        params.entry(key).or_default().push(value);
+    }
+
+    Ok(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_encode_round_trip() {
+        let value = "John Doe & Sons/caf\u{e9} 100%";
+        let encoded = percent_encode(value);
+
+        assert_eq!(encoded, "John%20Doe%20%26%20Sons%2Fcaf%C3%A9%20100%25");
+        assert_eq!(percent_decode(&encoded), Ok(value.to_string()));
+        assert_eq!(percent_encode("a-b_c.d~e"), "a-b_c.d~e");
+    }
+
+    #[test]
+    fn percent_decode_invalid_sequences() {
+        assert!(percent_decode("100%").is_err());
+        assert!(percent_decode("%2").is_err());
+        assert!(percent_decode("%zz").is_err());
+        assert!(percent_decode("%+1").is_err());
+        assert!(percent_decode("%C3%28").is_err());
+        assert_eq!(percent_decode("a%2fb"), Ok("a/b".to_string()));
+    }
+
+    #[test]
+    fn encode_query_sorted() {
+        let mut params = HashMap::new();
+        params.insert("q".to_string(), vec!["rust http".to_string()]);
+        params.insert("tag".to_string(), vec!["a&b".to_string(), "c".to_string()]);
+        params.insert("flag".to_string(), vec![]);
+
+        assert_eq!(encode_query(&params), "flag&q=rust%20http&tag=a%26b&tag=c");
+        assert_eq!(encode_query(&HashMap::new()), "");
+    }
+
+    #[test]
+    fn decode_query_repeated_keys() {
+        let params = decode_query("name=John+Doe&tag=a%26b&tag=c&&flag&empty=").unwrap();
+
+        assert_eq!(params["name"], vec!["John Doe"]);
+        assert_eq!(params["tag"], vec!["a&b", "c"]);
+        assert_eq!(params["flag"], vec![""]);
+        assert_eq!(params["empty"], vec![""]);
+        assert_eq!(params.len(), 4);
+
+        assert!(decode_query("a=%zz").is_err());
+        assert_eq!(decode_query(""), Ok(HashMap::new()));
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use crate::base64::{base64_decode, base64_encode};
+use crate::hash::sha1;
+use crate::http::{has_connection_token, HttpMethod, HttpRequest, HttpRequestError, HttpResponse, HttpStatusCode, KnownHeader};
+
+// Appended to the client key before hashing (RFC 6455 section 1.3)
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+// The Sec-WebSocket-Accept value the server answers a Sec-WebSocket-Key with
+pub fn websocket_accept_key(key: &str) -> String {
+    let mut input = key.trim().to_string();
+    input.push_str(WEBSOCKET_GUID);
+    base64_encode(&sha1(input.as_bytes()))
+}
+
+// Checks that request is a WebSocket opening handshake and builds the 101 response that
+// completes it. Only the handshake is handled, framing is up to the caller.
+pub fn websocket_handshake(request: &HttpRequest) -> Result<HttpResponse, HttpRequestError> {
+    if request.method != HttpMethod::GET {
+        return Err(HttpRequestError::InvalidRequest("WebSocket handshake must use GET".to_string()));
+    }
+
+    match request.header("Upgrade") {
+        Some(KnownHeader::Upgrade(protocols)) if protocols.split(',').any(|p| p.trim().eq_ignore_ascii_case("websocket")) => {}
+        _ => return Err(HttpRequestError::InvalidHeader("Expected Upgrade: websocket".to_string())),
+    }
+
+    if !has_connection_token(&request.headers, "upgrade") {
+        return Err(HttpRequestError::InvalidHeader("Expected Connection: Upgrade".to_string()));
+    }
+
+    match request.header("Sec-WebSocket-Version") {
+        Some(KnownHeader::Other(version)) if version.trim() == "13" => {}
+        _ => return Err(HttpRequestError::InvalidHeader("Expected Sec-WebSocket-Version: 13".to_string())),
+    }
+
+    // the key is a base64 encoded random 16 byte nonce
+    let key = match request.header("Sec-WebSocket-Key") {
+        Some(KnownHeader::SecWebSocketKey(key)) if base64_decode(key.trim()).is_ok_and(|nonce| nonce.len() == 16) => key,
+        _ => return Err(HttpRequestError::InvalidHeader("Invalid Sec-WebSocket-Key".to_string())),
+    };
+
+    let mut headers = HashMap::new();
+    headers.insert("Upgrade".to_string(), KnownHeader::Upgrade("websocket".to_string()));
+    headers.insert("Connection".to_string(), KnownHeader::Connection("Upgrade".to_string()));
+    headers.insert("Sec-WebSocket-Accept".to_string(), KnownHeader::SecWebSocketAccept(websocket_accept_key(key)));
+
+    Ok(HttpResponse {
+        version: request.version.clone(),
+        status_code: HttpStatusCode::SwitchingProtocols,
+        headers,
+        cookies: Vec::new(),
+        body: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{read_http_request, write_http_response};
+
+    const HANDSHAKE: &str = "GET /chat HTTP/1.1\r\nHost: server.example.com\r\nUpgrade: websocket\r\n\
+        Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nOrigin: http://example.com\r\n\
+        Sec-WebSocket-Version: 13\r\n\r\n";
+
+    #[test]
+    fn websocket_accept_key_rfc_example() {
+        assert_eq!(websocket_accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn websocket_handshake_response() {
+        let request = read_http_request(HANDSHAKE).unwrap();
+        assert_eq!(
+            request.header("Sec-WebSocket-Key"),
+            Some(&KnownHeader::SecWebSocketKey("dGhlIHNhbXBsZSBub25jZQ==".to_string()))
+        );
+
+        let response = websocket_handshake(&request).unwrap();
+        assert_eq!(
+            write_http_response(response).unwrap(),
+            "HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\n\
+            Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\nUpgrade: websocket\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn websocket_handshake_invalid_requests() {
+        let invalid = [
+            HANDSHAKE.replace("GET", "POST"),
+            HANDSHAKE.replace("Upgrade: websocket", "Upgrade: h2c"),
+            HANDSHAKE.replace("Connection: Upgrade", "Connection: keep-alive"),
+            HANDSHAKE.replace("Sec-WebSocket-Version: 13", "Sec-WebSocket-Version: 8"),
+            HANDSHAKE.replace("dGhlIHNhbXBsZSBub25jZQ==", "c2hvcnQ="),
+        ];
+
+        for request_str in invalid {
+            let request = read_http_request(&request_str).unwrap();
+            assert!(websocket_handshake(&request).is_err(), "accepted: {}", request_str);
+        }
+
+        // Connection may list other tokens as well
+        let request = read_http_request(&HANDSHAKE.replace("Connection: Upgrade", "Connection: keep-alive, Upgrade")).unwrap();
+        assert!(websocket_handshake(&request).is_ok());
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+
+// A simple representation of XML parsing errors
+#[derive(Debug, PartialEq)]
+pub enum XmlParserError {
+    UnexpectedToken(String),
+    InvalidSyntax(String),
+    MissingToken(String),
+    MismatchedTag(String),
+    EmptyInput,
+    NotSupported(String),
+}
+
+// A simple representation of an XML element, text content is trimmed and concatenated
+#[derive(Debug, PartialEq, Clone)]
+pub struct XmlNode {
+    pub name: String,
+    pub attributes: HashMap<String, String>,
+    pub children: Vec<XmlNode>,
+    pub text: String,
+}
+
+pub fn parse_xml(mut input: &str) -> Result<XmlNode, XmlParserError> {
+    if input.trim().is_empty() {
+        return Err(XmlParserError::EmptyInput);
+    }
+
+    input = skip_misc(input)?;
+
+    if !input.starts_with('<') {
+        return Err(XmlParserError::UnexpectedToken(format!(
+            "Unexpected token: {}",
+            input.chars().next().unwrap_or(' ')
+        )));
+    }
+
+    let (node, rest) = parse_element(input)?;
+
+    let rest = skip_misc(rest)?;
+    if !rest.is_empty() {
+        return Err(XmlParserError::UnexpectedToken(format!(
+            "Unexpected content after root element: {}",
+            rest
+        )));
+    }
+
+    Ok(node)
+}
+
+// Skips whitespace, the prolog (<?xml ... ?>) and comments between elements
+fn skip_misc(mut input: &str) -> Result<&str, XmlParserError> {
+    loop {
+        input = input.trim_start();
+
+        if input.starts_with("<?") {
+            match input.find("?>") {
+                Some(end) => input = &input[end + 2..],
+                None => return Err(XmlParserError::MissingToken("Missing '?>' for processing instruction".to_string())),
+            }
+        } else if input.starts_with("<!--") {
+            input = skip_comment(input)?;
+        } else {
+            return Ok(input);
+        }
+    }
+}
+
+fn skip_comment(input: &str) -> Result<&str, XmlParserError> {
+    match input[4..].find("-->") {
+        Some(end) => Ok(&input[4 + end + 3..]),
+        None => Err(XmlParserError::MissingToken("Missing '-->' for comment".to_string())),
+    }
+}
+
+fn parse_name(input: &str) -> Result<(String, &str), XmlParserError> {
+    let end = input
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-' || c == '.' || c == ':'))
+        .unwrap_or(input.len());
+
+    if end == 0 {
+        return Err(XmlParserError::InvalidSyntax(format!("Invalid name: {}", input)));
+    }
+
+    Ok((input[..end].to_string(), &input[end..]))
+}
+
+fn decode_entities(input: &str) -> Result<String, XmlParserError> {
+    let mut result = String::new();
+    let mut rest = input;
+
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => return Err(XmlParserError::MissingToken(format!("Missing ';' for entity: {}", rest))),
+        };
+
+        match &rest[1..end] {
+            "lt" => result.push('<'),
+            "gt" => result.push('>'),
+            "amp" => result.push('&'),
+            "quot" => result.push('"'),
+            "apos" => result.push('\''),
+            entity => return Err(XmlParserError::NotSupported(format!("Unknown entity: &{};", entity))),
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+fn parse_element(input: &str) -> Result<(XmlNode, &str), XmlParserError> {
+    if !input.starts_with('<') {
+        return Err(XmlParserError::InvalidSyntax("Element must start with '<'".to_string()));
+    }
+
+    let (name, mut input) = parse_name(&input[1..])?;
+    let mut attributes = HashMap::new();
+
+    loop {
+        input = input.trim_start();
+
+        if let Some(rest) = input.strip_prefix("/>") {
+            let node = XmlNode {
+                name,
+                attributes,
+                children: Vec::new(),
+                text: String::new(),
+            };
+            return Ok((node, rest));
+        }
+
+        if let Some(rest) = input.strip_prefix('>') {
+            input = rest;
+            break;
+        }
+
+        if input.is_empty() {
+            return Err(XmlParserError::MissingToken(format!("Expected '>' for <{}>", name)));
+        }
+
+        let (attribute_name, rest) = parse_name(input)?;
+
+        let rest = match rest.trim_start().strip_prefix('=') {
+            Some(rest) => rest.trim_start(),
+            None => return Err(XmlParserError::MissingToken(format!("Expected '=' after attribute {}", attribute_name))),
+        };
+
+        let quote = match rest.chars().next() {
+            Some(c) if c == '"' || c == '\'' => c,
+            _ => return Err(XmlParserError::InvalidSyntax(format!("Attribute value must be quoted: {}", attribute_name))),
+        };
+
+        let end = match rest[1..].find(quote) {
+            Some(end) => end,
+            None => return Err(XmlParserError::MissingToken(format!("Missing closing quote for attribute {}", attribute_name))),
+        };
+
+        attributes.insert(attribute_name, decode_entities(&rest[1..end + 1])?);
+        input = &rest[end + 2..];
+    }
+
+    let mut children = Vec::new();
+    let mut text = String::new();
+
+    loop {
+        if input.is_empty() {
+            return Err(XmlParserError::MissingToken(format!("Missing closing tag for <{}>", name)));
+        }
+
+        if let Some(rest) = input.strip_prefix("</") {
+            let (closing_name, rest) = parse_name(rest)?;
+            if closing_name != name {
+                return Err(XmlParserError::MismatchedTag(format!(
+                    "Expected </{}>, found </{}>",
+                    name, closing_name
+                )));
+            }
+
+            let rest = match rest.trim_start().strip_prefix('>') {
+                Some(rest) => rest,
+                None => return Err(XmlParserError::MissingToken(format!("Expected '>' for </{}>", name))),
+            };
+
+            let node = XmlNode {
+                name,
+                attributes,
+                children,
+                text: text.trim().to_string(),
+            };
+            return Ok((node, rest));
+        } else if input.starts_with("<!--") {
+            input = skip_comment(input)?;
+        } else if let Some(rest) = input.strip_prefix("<![CDATA[") {
+            match rest.find("]]>") {
+                Some(end) => {
+                    text.push_str(&rest[..end]);
+                    input = &rest[end + 3..];
+                }
+                None => return Err(XmlParserError::MissingToken("Missing ']]>' for CDATA section".to_string())),
+            }
+        } else if input.starts_with('<') {
+            let (child, rest) = parse_element(input)?;
+            children.push(child);
+            input = rest;
+        } else {
+            let end = input.find('<').unwrap_or(input.len());
+            text.push_str(&decode_entities(&input[..end])?);
+            input = &input[end..];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_xml_nested_elements() {
+        let xml = r#"
+        <?xml version="1.0" encoding="UTF-8"?>
+        <person>
+            <name>John Doe</name>
+            <!-- the address is optional -->
+            <address>
+                <city>Anytown</city>
+                <zip>12345</zip>
+            </address>
+        </person>
+        "#;
+
+        let root = parse_xml(xml).unwrap();
+
+        assert_eq!(root.name, "person");
+        assert_eq!(root.children.len(), 2);
+        assert_eq!(root.children[0].name, "name");
+        assert_eq!(root.children[0].text, "John Doe");
+
+        let address = &root.children[1];
+        assert_eq!(address.name, "address");
+        assert_eq!(address.children[0].name, "city");
+        assert_eq!(address.children[0].text, "Anytown");
+        assert_eq!(address.children[1].name, "zip");
+        assert_eq!(address.children[1].text, "12345");
+    }
+
+    #[test]
+    fn read_xml_attributes() {
+        let root = parse_xml(r#"<course id="42" title='Math &amp; Science'>Algebra</course>"#).unwrap();
+
+        assert_eq!(root.name, "course");
+        assert_eq!(root.attributes.get("id"), Some(&"42".to_string()));
+        assert_eq!(root.attributes.get("title"), Some(&"Math & Science".to_string()));
+        assert_eq!(root.text, "Algebra");
+    }
+
+    #[test]
+    fn read_xml_self_closing_tag() {
+        let root = parse_xml(r#"<courses><course id="1"/><course id="2" /></courses>"#).unwrap();
+
+        assert_eq!(root.children.len(), 2);
+        assert_eq!(root.children[0].name, "course");
+        assert_eq!(root.children[0].attributes.get("id"), Some(&"1".to_string()));
+        assert!(root.children[0].children.is_empty());
+        assert_eq!(root.children[1].attributes.get("id"), Some(&"2".to_string()));
+    }
+
+    #[test]
+    fn read_xml_mismatched_tag() {
+        let result = parse_xml("<a><b></a></b>");
+
+        assert_eq!(
+            result,
+            Err(XmlParserError::MismatchedTag("Expected </b>, found </a>".to_string()))
+        );
+    }
+
+    #[test]
+    fn read_xml_missing_closing_tag() {
+        assert!(matches!(parse_xml("<a><b></b>"), Err(XmlParserError::MissingToken(_))));
+        assert_eq!(parse_xml("   "), Err(XmlParserError::EmptyInput));
+    }
+}
@@ -0,0 +1,160 @@
+// The Person/Address example from the api binary, kept here so the FromJson contract
+// and the accessors it relies on are checked against the sample document
+use parsing::{parse_json, FromJson, JsonType};
+
+const SAMPLE: &str = r#"
+    {
+        "name": "John Doe",
+        "age": 30,
+        "is_student": false,
+        "courses": ["Math", "Science", "History"],
+        "address": {
+            "street": "123 Main St",
+            "city": "Anytown",
+            "zip": "12345"
+        }
+    }
+    "#;
+
+#[derive(Debug, PartialEq)]
+struct Person {
+    name: String,
+    age: i32,
+    is_student: bool,
+    courses: Vec<String>,
+    address: Address,
+}
+
+impl FromJson for Person {
+    fn from_json(json: &JsonType) -> Self {
+        match json {
+            JsonType::Object(obj) => {
+                let name = if let Some(JsonType::String(s)) = obj.get("name") {
+                    s.clone()
+                } else {
+                    "".to_string()
+                };
+
+                let age = if let Some(JsonType::Number(n)) = obj.get("age") {
+                    *n as i32
+                } else {
+                    0
+                };
+
+                let is_student = if let Some(JsonType::Boolean(b)) = obj.get("is_student") {
+                    *b
+                } else {
+                    false
+                };
+
+                let courses = if let Some(JsonType::Array(arr)) = obj.get("courses") {
+                    arr.iter().filter_map(|item| {
+                        if let JsonType::String(s) = item {
+                            Some(s.clone())
+                        } else {
+                            None
+                        }
+                    }).collect()
+                } else {
+                    vec![]
+                };
+
+                let address = if let Some(addr_json) = obj.get("address") {
+                    Address::from_json(addr_json)
+                } else {
+                    Address {
+                        street: "".to_string(),
+                        city: "".to_string(),
+                        zip: "".to_string(),
+                    }
+                };
+
+                Person {
+                    name,
+                    age,
+                    is_student,
+                    courses,
+                    address,
+                }
+            },
+            _ => panic!("Expected a JSON object"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Address {
+    street: String,
+    city: String,
+    zip: String,
+}
+
+impl FromJson for Address {
+    fn from_json(json: &JsonType) -> Self {
+        match json {
+            JsonType::Object(obj) => {
+                let street = if let Some(JsonType::String(s)) = obj.get("street") {
+                    s.clone()
+                } else {
+                    "".to_string()
+                };
+
+                let city = if let Some(JsonType::String(s)) = obj.get("city") {
+                    s.clone()
+                } else {
+                    "".to_string()
+                };
+
+                let zip = if let Some(JsonType::String(s)) = obj.get("zip") {
+                    s.clone()
+                } else {
+                    "".to_string()
+                };
+
+                Address {
+                    street,
+                    city,
+                    zip,
+                }
+            },
+            _ => panic!("Expected a JSON object"),
+        }
+    }
+}
+
+#[test]
+fn person_from_sample() {
+    let person = Person::from_json(&parse_json(SAMPLE).unwrap());
+
+    assert_eq!(person.name, "John Doe");
+    assert_eq!(person.age, 30);
+    assert!(!person.is_student);
+    assert_eq!(person.courses, vec!["Math", "Science", "History"]);
+    assert_eq!(
+        person.address,
+        Address {
+            street: "123 Main St".to_string(),
+            city: "Anytown".to_string(),
+            zip: "12345".to_string(),
+        }
+    );
+}
+
+#[test]
+fn person_missing_fields_fall_back_to_defaults() {
+    let person = Person::from_json(&parse_json(r#"{"name": "Jane", "is_student": true, "courses": ["Art", 1]}"#).unwrap());
+
+    assert_eq!(person.name, "Jane");
+    assert_eq!(person.age, 0);
+    assert!(person.is_student);
+    assert_eq!(person.courses, vec!["Art"]);
+    assert_eq!(person.address.street, "");
+    assert_eq!(person.address.city, "");
+    assert_eq!(person.address.zip, "");
+}
+
+#[test]
+#[should_panic(expected = "Expected a JSON object")]
+fn person_from_non_object_panics() {
+    Person::from_json(&JsonType::Null);
+}